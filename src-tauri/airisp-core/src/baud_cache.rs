@@ -0,0 +1,53 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{Mutex, PoisonError};
+use lazy_static::lazy_static;
+use rust_i18n::t;
+use crate::config_file::ConfigFile;
+use crate::log::LOG;
+
+lazy_static! {
+    /// gang 模式下多个线程会同时更新缓存，读取、修改、写回文件的过程需要串行执行
+    static ref LOCK: Mutex<()> = Mutex::new(());
+}
+
+/// 自动波特率找到的稳定波特率，按串口和USB转串口芯片保存在用户配置目录下的 baud_cache.json
+fn path() -> Option<PathBuf> {
+    ConfigFile::user_dir().map(|dir| dir.join("baud_cache.json"))
+}
+
+fn load() -> BTreeMap<String, u32> {
+    path()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+/// 串口名称和USB VID/PID组成的键，同一个串口换了转换器后需要重新测试
+pub fn key(port: &str, vid: Option<u16>, pid: Option<u16>) -> String {
+    match (vid, pid) {
+        (Some(vid), Some(pid)) => format!("{} {:04x}:{:04x}", port, vid, pid),
+        _ => port.to_string(),
+    }
+}
+
+pub fn get(key: &str) -> Option<u32> {
+    load().get(key).copied()
+}
+
+/// 记录稳定的波特率，写入失败只给出警告
+pub fn set(key: &str, baud: u32) {
+    let Some(path) = path() else {
+        return;
+    };
+    let _guard = LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+    let mut cache = load();
+    cache.insert(key.to_string(), baud);
+    let result = path
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|_| std::fs::write(&path, serde_json::to_string_pretty(&cache).unwrap()));
+    if let Err(e) = result {
+        LOG.warn(t!("baud_cache_fail_help", "path" => path.display(), "error" => e).as_str());
+    }
+}
@@ -0,0 +1,278 @@
+use std::collections::BTreeMap;
+use crate::error::Error;
+use crate::reset_sequence::{self, ResetSequence};
+use std::io::{Read, Write};
+use std::path::Path;
+use crate::{elf_to_bin, hex_to_bin, peripheral, srec_to_bin, uf2};
+
+/// 连接和烧录时使用的配置，通过 `Config::builder()` 创建
+///
+/// ```no_run
+/// use airisp_core::config::Config;
+///
+/// let config = Config::builder()
+///     .port("/dev/ttyUSB0")
+///     .baud(115200)
+///     .chip("air001")
+///     .build();
+/// let mut handle = config.get_peripheral_handle()?;
+/// let pp = handle.get_pp();
+/// pp.reset_bootloader()?;
+/// # Ok::<(), airisp_core::error::Error>(())
+/// ```
+#[derive(Clone)]
+pub struct Config {
+    port: String,
+    baud: u32,
+    auto_baud: bool,
+    chip: String,
+    connect_attempts: u32,
+    before: String,
+    after: String,
+    peripheral: String,
+    /// 命名的复位序列，可以在 before 和 after 中使用名称
+    reset_sequences: BTreeMap<String, String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            port: "auto".to_string(),
+            baud: 0,
+            auto_baud: false,
+            chip: "auto".to_string(),
+            connect_attempts: 10,
+            before: "default_reset".to_string(),
+            after: "hard_reset".to_string(),
+            peripheral: "Uart".to_string(),
+            reset_sequences: BTreeMap::new(),
+        }
+    }
+}
+
+pub struct ConfigBuilder {
+    config: Config,
+}
+
+impl ConfigBuilder {
+    /// 串口名称或者调试器名称，"auto" 表示自动选择
+    pub fn port(mut self, port: &str) -> Self {
+        self.config.port = port.to_string();
+        self
+    }
+
+    /// 串口波特率或者SWD速度（kHz），0 表示使用默认值
+    pub fn baud(mut self, baud: u32) -> Self {
+        self.config.baud = baud;
+        self
+    }
+
+    /// 握手时从安全的波特率开始逐级提高，选择稳定的最高波特率，baud 不为0时作为上限
+    pub fn auto_baud(mut self, auto_baud: bool) -> Self {
+        self.config.auto_baud = auto_baud;
+        self
+    }
+
+    /// 芯片型号，"auto" 表示自动识别
+    pub fn chip(mut self, chip: &str) -> Self {
+        self.config.chip = chip.to_string();
+        self
+    }
+
+    pub fn connect_attempts(mut self, connect_attempts: u32) -> Self {
+        self.config.connect_attempts = connect_attempts;
+        self
+    }
+
+    /// 进入bootloader的方式，"default_reset"、"direct_connect"、命名的复位序列或者直接写出的序列
+    pub fn before(mut self, before: &str) -> Self {
+        self.config.before = before.to_string();
+        self
+    }
+
+    /// 烧录完成后的操作，"hard_reset"、"go"、命名的复位序列或者直接写出的序列
+    pub fn after(mut self, after: &str) -> Self {
+        self.config.after = after.to_string();
+        self
+    }
+
+    /// 添加命名的复位序列，例如 ("inverted", "dtr=0,rts=1,sleep=20,rts=0")
+    pub fn reset_sequence(mut self, name: &str, sequence: &str) -> Self {
+        self.config.reset_sequences.insert(name.to_string(), sequence.to_string());
+        self
+    }
+
+    /// 使用的外设，"Uart" 或 "Swd"
+    pub fn peripheral(mut self, peripheral: &str) -> Self {
+        self.config.peripheral = peripheral.to_string();
+        self
+    }
+
+    pub fn build(self) -> Config {
+        self.config
+    }
+}
+
+impl Config
+{
+    pub fn builder() -> ConfigBuilder
+    {
+        ConfigBuilder {
+            config: Config::default(),
+        }
+    }
+
+    /// 以当前配置为基础修改部分参数，例如 gang 模式下为每个设备设置不同的端口
+    pub fn to_builder(&self) -> ConfigBuilder
+    {
+        ConfigBuilder {
+            config: self.clone(),
+        }
+    }
+
+    pub fn get_port(&self) -> String
+    {
+        self.port.clone()
+    }
+    pub fn get_baud(&self) -> u32
+    {
+        self.baud
+    }
+    pub fn get_auto_baud(&self) -> bool
+    {
+        self.auto_baud
+    }
+    pub fn get_connect_attempts(&self) -> u32
+    {
+        self.connect_attempts
+    }
+
+    pub fn get_before(&self) -> String
+    {
+        self.before.clone()
+    }
+
+    pub fn get_after(&self) -> String
+    {
+        self.after.clone()
+    }
+
+    /// 进入bootloader时使用的复位序列
+    pub fn get_before_sequence(&self) -> Result<ResetSequence, Error>
+    {
+        ResetSequence::resolve(&self.before, &self.reset_sequences)
+    }
+
+    /// 烧录完成后使用的复位序列，"go" 时返回 None，通过Go指令跳转
+    pub fn get_after_sequence(&self) -> Result<Option<ResetSequence>, Error>
+    {
+        match self.after.as_str() {
+            "go" => Ok(None),
+            "hard_reset" if self.before == "direct_connect" => {
+                ResetSequence::parse(reset_sequence::DIRECT_CONNECT_HARD_RESET).map(Some)
+            }
+            "hard_reset" => ResetSequence::parse(reset_sequence::HARD_RESET).map(Some),
+            after => ResetSequence::resolve(after, &self.reset_sequences).map(Some),
+        }
+    }
+
+    pub fn get_chip(&self) -> String
+    {
+        self.chip.clone()
+    }
+
+    pub fn get_peripheral(&self) -> String
+    {
+        self.peripheral.clone()
+    }
+
+    pub fn get_peripheral_handle(&self) -> Result<peripheral::Peripheral<'_>, Error>
+    {
+        // 全部转换为小写
+        let peripheral = self.get_peripheral().to_lowercase();
+        match peripheral.as_str() {
+            "swd" => {
+                let p = peripheral::Peripheral::Swd(peripheral::swd::Swd::new(self)?);
+                Ok(p)
+            },
+            "uart" => {
+                let p = peripheral::Peripheral::GeneralUart(peripheral::general_uart::GeneralUart::new(self)?);
+                Ok(p)
+            },
+            _ => {
+                Err(Error::Unsupported("not support peripheral".to_string()))
+            }
+        }
+    }
+
+    /// 读取固件文件，family_id 为识别到的芯片的UF2系列，UF2文件只保留该系列的块
+    pub fn read_file(&self, file_path: &str, family_id: Option<u32>) -> Result<hex_to_bin::Firmware, Error>
+    {
+        // 根据文件后缀判断文件格式
+        let mut file = std::fs::File::open(file_path)?;
+        // 如果文件为空，直接返回
+        if file.metadata()?.len() == 0 {
+           return Err(Error::FileFormat("file is empty".to_string()));
+        }
+        // 读取后缀名
+        let path = Path::new(file_path);
+        let suffix = path.extension().and_then(|s| s.to_str()).unwrap_or("").to_lowercase();
+        let mut data = Vec::new();
+        file.read_to_end(&mut data)?;
+        let firmware = match suffix.as_str() {
+            "hex" => {
+                let hex = String::from_utf8(data)?;
+                hex_to_bin::hex_to_bin(&hex)?
+            }
+            "srec" | "s19" | "s28" | "s37" | "mot" => {
+                let srec = String::from_utf8(data)?;
+                srec_to_bin::srec_to_bin(&srec)?
+            }
+            "uf2" => {
+                uf2::uf2_to_bin(&data, family_id)?
+            }
+            "elf" | "axf" | "out" => {
+                elf_to_bin::elf_to_bin(&data)?
+            }
+            // 未知后缀时根据文件头判断是否为ELF或UF2文件
+            _ if elf_to_bin::is_elf(&data) => {
+                elf_to_bin::elf_to_bin(&data)?
+            }
+            _ if uf2::is_uf2(&data) => {
+                uf2::uf2_to_bin(&data, family_id)?
+            }
+            _ => {
+                hex_to_bin::Firmware {
+                    bins: vec![hex_to_bin::Bin {
+                        address: 0xFFFF_FFFF,
+                        data,
+                    }],
+                    start_address: None,
+                }
+            }
+        };
+        Ok(firmware)
+    }
+
+    pub fn write_file(&self, file_path: &str, vec_bin: &[hex_to_bin::Bin], family_id: Option<u32>) -> Result<(), Error>
+    {
+        // 根据后缀名决定输出格式，默认输出为bin
+        let path = Path::new(file_path);
+        let suffix = path.extension().and_then(|s| s.to_str()).unwrap_or("").to_lowercase();
+        let mut file = std::fs::File::create(file_path)?;
+        match suffix.as_str() {
+            "hex" => {
+                file.write_all(hex_to_bin::bin_to_hex(vec_bin).as_bytes())?;
+            }
+            "uf2" => {
+                file.write_all(&uf2::bin_to_uf2(vec_bin, family_id))?;
+            }
+            _ => {
+                for bin in vec_bin {
+                    file.write_all(&bin.data)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,269 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use rust_i18n::t;
+use serde::Deserialize;
+use crate::config::ConfigBuilder;
+use crate::error::Error;
+
+/// 项目配置文件的文件名
+pub const FILE_NAME: &str = "airisp.toml";
+
+/// airisp.toml 的内容，没有填写的项使用命令行参数的默认值
+///
+/// ```toml
+/// port = "/dev/ttyUSB0"
+/// baud = 115200
+/// auto_baud = false
+/// chip = "air001"
+///
+/// before = "inverted"
+///
+/// [reset_sequences]
+/// inverted = "dtr=0,rts=1,sleep=20,rts=0,dtr=1,sleep=5,dtr=0"
+///
+/// [profiles.app]
+/// erase_pages = true
+/// images = [
+///     { address = 0x08000000, path = "build/app.bin" },
+/// ]
+/// ```
+#[derive(Deserialize, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigFile {
+    pub port: Option<String>,
+    pub baud: Option<u32>,
+    pub auto_baud: Option<bool>,
+    pub chip: Option<String>,
+    pub connect_attempts: Option<u32>,
+    pub before: Option<String>,
+    pub after: Option<String>,
+    pub peripheral: Option<String>,
+    /// 烧录方案，通过 write_flash --profile 使用
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+    /// 命名的复位序列，通过 --before/--after 使用
+    #[serde(default)]
+    pub reset_sequences: BTreeMap<String, String>,
+}
+
+/// 一组要烧录的文件和烧录选项
+#[derive(Deserialize, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    pub images: Vec<ProfileImage>,
+    pub erase_all: Option<bool>,
+    pub erase_pages: Option<bool>,
+    pub diff: Option<bool>,
+    pub verify: Option<bool>,
+}
+
+/// 烧录地址和文件路径，相对路径以配置文件所在目录为准
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ProfileImage {
+    pub address: u32,
+    pub path: String,
+}
+
+impl ConfigFile {
+    /// 读取并解析配置文件
+    pub fn load(path: &Path) -> Result<ConfigFile, Error> {
+        let text = std::fs::read_to_string(path)?;
+        let mut file: ConfigFile = toml::from_str(&text)
+            .map_err(|e| Error::FileFormat(format!("{}: {}", path.display(), e)))?;
+        // 文件路径相对于配置文件所在目录，而不是当前目录
+        let dir = path.parent().unwrap_or(Path::new("."));
+        for profile in file.profiles.values_mut() {
+            for image in profile.images.iter_mut() {
+                if Path::new(&image.path).is_relative() {
+                    image.path = dir.join(&image.path).to_string_lossy().to_string();
+                }
+            }
+        }
+        Ok(file)
+    }
+
+    /// 从 start 开始向上逐级查找 airisp.toml
+    pub fn find(start: &Path) -> Option<PathBuf> {
+        start
+            .ancestors()
+            .map(|dir| dir.join(FILE_NAME))
+            .find(|path| path.is_file())
+    }
+
+    /// 用户级配置目录，也用于保存自动波特率的结果
+    ///
+    /// Windows 下为 %APPDATA%\AirISP，其他系统为 $XDG_CONFIG_HOME/AirISP 或 ~/.config/AirISP
+    pub fn user_dir() -> Option<PathBuf> {
+        let dir = if cfg!(windows) {
+            std::env::var_os("APPDATA").map(PathBuf::from)
+        } else {
+            std::env::var_os("XDG_CONFIG_HOME")
+                .map(PathBuf::from)
+                .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        };
+        dir.map(|dir| dir.join("AirISP"))
+    }
+
+    /// 用户级配置文件的位置
+    pub fn user_path() -> Option<PathBuf> {
+        ConfigFile::user_dir().map(|dir| dir.join(FILE_NAME))
+    }
+
+    /// 依次读取用户级配置文件和当前目录向上找到的项目配置文件，项目配置优先
+    ///
+    /// 返回合并后的配置和实际读取的文件
+    pub fn discover() -> Result<(ConfigFile, Vec<PathBuf>), Error> {
+        let mut paths = Vec::new();
+        if let Some(path) = ConfigFile::user_path().filter(|path| path.is_file()) {
+            paths.push(path);
+        }
+        if let Some(path) = ConfigFile::find(&std::env::current_dir()?) {
+            if !paths.contains(&path) {
+                paths.push(path);
+            }
+        }
+        let mut file = ConfigFile::default();
+        for path in paths.iter() {
+            file = file.merge(ConfigFile::load(path)?);
+        }
+        Ok((file, paths))
+    }
+
+    /// 合并两个配置，other 中填写的项覆盖当前的值，同名的烧录方案整个替换
+    pub fn merge(self, other: ConfigFile) -> ConfigFile {
+        let mut profiles = self.profiles;
+        profiles.extend(other.profiles);
+        let mut reset_sequences = self.reset_sequences;
+        reset_sequences.extend(other.reset_sequences);
+        ConfigFile {
+            port: other.port.or(self.port),
+            baud: other.baud.or(self.baud),
+            auto_baud: other.auto_baud.or(self.auto_baud),
+            chip: other.chip.or(self.chip),
+            connect_attempts: other.connect_attempts.or(self.connect_attempts),
+            before: other.before.or(self.before),
+            after: other.after.or(self.after),
+            peripheral: other.peripheral.or(self.peripheral),
+            profiles,
+            reset_sequences,
+        }
+    }
+
+    /// 把配置文件中填写的项应用到 builder 上
+    pub fn apply(&self, mut builder: ConfigBuilder) -> ConfigBuilder {
+        if let Some(port) = &self.port {
+            builder = builder.port(port);
+        }
+        if let Some(baud) = self.baud {
+            builder = builder.baud(baud);
+        }
+        if let Some(auto_baud) = self.auto_baud {
+            builder = builder.auto_baud(auto_baud);
+        }
+        if let Some(chip) = &self.chip {
+            builder = builder.chip(chip);
+        }
+        if let Some(connect_attempts) = self.connect_attempts {
+            builder = builder.connect_attempts(connect_attempts);
+        }
+        if let Some(before) = &self.before {
+            builder = builder.before(before);
+        }
+        if let Some(after) = &self.after {
+            builder = builder.after(after);
+        }
+        if let Some(peripheral) = &self.peripheral {
+            builder = builder.peripheral(peripheral);
+        }
+        for (name, sequence) in self.reset_sequences.iter() {
+            builder = builder.reset_sequence(name, sequence);
+        }
+        builder
+    }
+
+    pub fn profile(&self, name: &str) -> Result<&Profile, Error> {
+        self.profiles
+            .get(name)
+            .ok_or_else(|| Error::InvalidInput(t!("profile_not_found_help", "name" => name)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 在系统临时目录下建立一个空的测试目录
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("airisp-test-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn find() {
+        let root = temp_dir("find");
+        let project = root.join("project");
+        let src = project.join("src").join("app");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::write(root.join(FILE_NAME), "").unwrap();
+        std::fs::write(project.join(FILE_NAME), "").unwrap();
+        // 向上查找时停在最近的一个配置文件
+        assert_eq!(ConfigFile::find(&src), Some(project.join(FILE_NAME)));
+        assert_eq!(ConfigFile::find(&project), Some(project.join(FILE_NAME)));
+        assert_eq!(ConfigFile::find(&root), Some(root.join(FILE_NAME)));
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn load_profile() {
+        let dir = temp_dir("load");
+        let path = dir.join(FILE_NAME);
+        std::fs::write(&path, r#"
+chip = "air001"
+
+[profiles.app]
+erase_pages = true
+images = [
+    { address = 0x08000000, path = "build/app.bin" },
+]
+"#).unwrap();
+        let file = ConfigFile::load(&path).unwrap();
+        assert_eq!(file.chip.as_deref(), Some("air001"));
+        // 相对路径以配置文件所在目录为准
+        let profile = file.profile("app").unwrap();
+        assert_eq!(profile.erase_pages, Some(true));
+        assert_eq!(profile.images[0].address, 0x0800_0000);
+        assert_eq!(PathBuf::from(&profile.images[0].path), dir.join("build").join("app.bin"));
+        assert!(matches!(file.profile("ap"), Err(Error::InvalidInput(_))));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn merge() {
+        let user: ConfigFile = toml::from_str(r#"
+port = "COM3"
+baud = 115200
+[reset_sequences]
+inverted = "dtr=0"
+[profiles.app]
+images = [{ address = 0x08000000, path = "user.bin" }]
+"#).unwrap();
+        let project: ConfigFile = toml::from_str(r#"
+port = "COM4"
+[profiles.app]
+verify = false
+images = [{ address = 0x08001000, path = "project.bin" }]
+"#).unwrap();
+        // 后读取的项目配置覆盖用户配置中填写了的项，同名的烧录方案整个替换
+        let file = user.merge(project);
+        assert_eq!(file.port.as_deref(), Some("COM4"));
+        assert_eq!(file.baud, Some(115200));
+        assert_eq!(file.reset_sequences.get("inverted").map(String::as_str), Some("dtr=0"));
+        let profile = file.profile("app").unwrap();
+        assert_eq!(profile.verify, Some(false));
+        assert_eq!(profile.images.len(), 1);
+        assert_eq!(profile.images[0].path, "project.bin");
+    }
+}
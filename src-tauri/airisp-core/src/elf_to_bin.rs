@@ -0,0 +1,339 @@
+use crate::error::Error;
+use crate::hex_to_bin::{self, Bin, Firmware};
+
+const PT_LOAD: u32 = 1;
+const SHT_NOBITS: u32 = 8;
+const SHF_ALLOC: u64 = 0x2;
+
+struct ProgramHeader {
+    p_type: u32,
+    offset: u64,
+    vaddr: u64,
+    paddr: u64,
+    filesz: u64,
+}
+
+struct SectionHeader {
+    sh_type: u32,
+    flags: u64,
+    addr: u64,
+    size: u64,
+}
+
+fn format_error(msg: &str) -> Error {
+    Error::FileFormat(format!("elf: {}", msg))
+}
+
+/// 按照ELF头中的位数和字节序读取数据
+struct Reader<'a> {
+    data: &'a [u8],
+    is_64: bool,
+    is_le: bool,
+}
+
+impl Reader<'_> {
+    fn bytes(&self, offset: u64, len: u64) -> Result<&[u8], Error> {
+        let end = offset.checked_add(len).ok_or_else(|| format_error("offset overflow"))?;
+        if end > self.data.len() as u64 {
+            return Err(format_error("file is truncated"));
+        }
+        Ok(&self.data[offset as usize..end as usize])
+    }
+
+    fn u16(&self, offset: u64) -> Result<u16, Error> {
+        let b = self.bytes(offset, 2)?;
+        let b = [b[0], b[1]];
+        Ok(if self.is_le { u16::from_le_bytes(b) } else { u16::from_be_bytes(b) })
+    }
+
+    fn u32(&self, offset: u64) -> Result<u32, Error> {
+        let b = self.bytes(offset, 4)?;
+        let b = [b[0], b[1], b[2], b[3]];
+        Ok(if self.is_le { u32::from_le_bytes(b) } else { u32::from_be_bytes(b) })
+    }
+
+    fn u64(&self, offset: u64) -> Result<u64, Error> {
+        let b = self.bytes(offset, 8)?;
+        let b = [b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]];
+        Ok(if self.is_le { u64::from_le_bytes(b) } else { u64::from_be_bytes(b) })
+    }
+
+    /// 计算头表中第 index 项的偏移，并确认整项都在文件内，文件中的值有误时返回错误而不是溢出
+    fn entry(&self, table: u64, index: u64, size: u64) -> Result<u64, Error> {
+        let base = index
+            .checked_mul(size)
+            .and_then(|offset| table.checked_add(offset))
+            .ok_or_else(|| format_error("header table offset overflow"))?;
+        self.bytes(base, size)?;
+        Ok(base)
+    }
+
+    /// 读取地址大小的字段，32位为4字节，64位为8字节
+    fn addr(&self, offset: u64) -> Result<u64, Error> {
+        if self.is_64 {
+            self.u64(offset)
+        } else {
+            Ok(self.u32(offset)? as u64)
+        }
+    }
+}
+
+pub fn is_elf(data: &[u8]) -> bool {
+    data.len() >= 4 && &data[0..4] == b"\x7FELF"
+}
+
+pub fn elf_to_bin(data: &[u8]) -> Result<Firmware, Error> {
+    if !is_elf(data) || data.len() < 0x34 {
+        return Err(format_error("not an elf file"));
+    }
+    let is_64 = match data[4] {
+        1 => false,
+        2 => true,
+        _ => return Err(format_error("unknown elf class")),
+    };
+    let is_le = match data[5] {
+        1 => true,
+        2 => false,
+        _ => return Err(format_error("unknown elf data encoding")),
+    };
+    let r = Reader { data, is_64, is_le };
+
+    // ELF头中各字段的偏移，32位和64位不同
+    let (entry, phoff, shoff, field) = if is_64 {
+        (r.u64(0x18)?, r.u64(0x20)?, r.u64(0x28)?, 0x36)
+    } else {
+        (r.u32(0x18)? as u64, r.u32(0x1C)? as u64, r.u32(0x20)? as u64, 0x2A)
+    };
+    let phentsize = r.u16(field)? as u64;
+    let phnum = r.u16(field + 2)? as u64;
+    let shentsize = r.u16(field + 4)? as u64;
+    let shnum = r.u16(field + 6)? as u64;
+
+    let mut program_headers = Vec::new();
+    for i in 0..phnum {
+        let base = r.entry(phoff, i, phentsize)?;
+        let ph = if is_64 {
+            ProgramHeader {
+                p_type: r.u32(base)?,
+                offset: r.u64(base + 0x08)?,
+                vaddr: r.u64(base + 0x10)?,
+                paddr: r.u64(base + 0x18)?,
+                filesz: r.u64(base + 0x20)?,
+            }
+        } else {
+            ProgramHeader {
+                p_type: r.u32(base)?,
+                offset: r.u32(base + 0x04)? as u64,
+                vaddr: r.u32(base + 0x08)? as u64,
+                paddr: r.u32(base + 0x0C)? as u64,
+                filesz: r.u32(base + 0x10)? as u64,
+            }
+        };
+        // 只关心有文件内容的PT_LOAD段
+        if ph.p_type == PT_LOAD && ph.filesz > 0 {
+            program_headers.push(ph);
+        }
+    }
+
+    // 节头只用来找出NOBITS节（例如.bss），它们不需要烧录
+    let mut nobits = Vec::new();
+    for i in 0..shnum {
+        let base = r.entry(shoff, i, shentsize)?;
+        let sh = SectionHeader {
+            sh_type: r.u32(base + 0x04)?,
+            flags: r.addr(base + 0x08)?,
+            addr: if is_64 { r.u64(base + 0x10)? } else { r.u32(base + 0x0C)? as u64 },
+            size: if is_64 { r.u64(base + 0x20)? } else { r.u32(base + 0x14)? as u64 },
+        };
+        if sh.flags & SHF_ALLOC != 0 && sh.sh_type == SHT_NOBITS && sh.size > 0 {
+            nobits.push(sh);
+        }
+    }
+
+    let to_address = |address: u64| -> Result<u32, Error> {
+        u32::try_from(address).map_err(|_| format_error(&format!("address {:#x} is out of range", address)))
+    };
+
+    // 每个PT_LOAD段的文件内容整段放在物理地址(LMA)上，保留段内对齐用的填充，
+    // 如果段的文件内容覆盖到了NOBITS节，从该节开始截断
+    let mut vec_bin = Vec::new();
+    for ph in program_headers.iter() {
+        let mut filesz = ph.filesz;
+        for sh in nobits.iter() {
+            if sh.addr >= ph.vaddr && sh.addr - ph.vaddr < filesz {
+                filesz = sh.addr - ph.vaddr;
+            }
+        }
+        if filesz == 0 {
+            continue;
+        }
+        let address = to_address(ph.paddr)?;
+        let data = r.bytes(ph.offset, filesz)?;
+        if address as u64 + filesz > u32::MAX as u64 + 1 {
+            return Err(format_error(&format!("segment at {:#x} is out of range", address)));
+        }
+        vec_bin.push(Bin {
+            address,
+            data: data.to_vec(),
+        });
+    }
+
+    if vec_bin.is_empty() {
+        return Err(format_error("no loadable segment"));
+    }
+
+    Ok(Firmware {
+        bins: hex_to_bin::merge(vec_bin),
+        start_address: to_address(entry).ok(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hex_to_bin::tests::segments;
+
+    const SHT_PROGBITS: u32 = 1;
+
+    /// 生成32位的ELF文件，segments 为 (类型, 物理地址, 内容)，
+    /// sections 为 (类型, 标志, 所在段的序号, 在段内的偏移, 大小)
+    fn elf32(is_le: bool, entry: u32, segments: &[(u32, u32, &[u8])], sections: &[(u32, u32, usize, u32, u32)]) -> Vec<u8> {
+        let put16 = |out: &mut Vec<u8>, offset: usize, value: u16| {
+            let b = if is_le { value.to_le_bytes() } else { value.to_be_bytes() };
+            out[offset..offset + 2].copy_from_slice(&b);
+        };
+        let put32 = |out: &mut Vec<u8>, offset: usize, value: u32| {
+            let b = if is_le { value.to_le_bytes() } else { value.to_be_bytes() };
+            out[offset..offset + 4].copy_from_slice(&b);
+        };
+
+        let mut out = vec![0u8; 0x34 + 0x20 * segments.len()];
+        out[0..4].copy_from_slice(b"\x7FELF");
+        out[4] = 1;
+        out[5] = if is_le { 1 } else { 2 };
+        out[6] = 1;
+        put32(&mut out, 0x18, entry);
+        put32(&mut out, 0x1C, 0x34);
+        put16(&mut out, 0x2A, 0x20);
+        put16(&mut out, 0x2C, segments.len() as u16);
+        put16(&mut out, 0x2E, 0x28);
+        put16(&mut out, 0x30, sections.len() as u16);
+
+        let mut offsets = Vec::new();
+        for (i, (p_type, paddr, data)) in segments.iter().enumerate() {
+            let base = 0x34 + 0x20 * i;
+            let offset = out.len() as u32;
+            offsets.push(offset);
+            put32(&mut out, base, *p_type);
+            put32(&mut out, base + 0x04, offset);
+            // 虚拟地址和物理地址不同，检查使用的是物理地址
+            put32(&mut out, base + 0x08, paddr.wrapping_add(0x1000_0000));
+            put32(&mut out, base + 0x0C, *paddr);
+            put32(&mut out, base + 0x10, data.len() as u32);
+            put32(&mut out, base + 0x14, data.len() as u32);
+            out.extend_from_slice(data);
+        }
+
+        let shoff = out.len();
+        put32(&mut out, 0x20, shoff as u32);
+        for (i, (sh_type, flags, segment, offset, size)) in sections.iter().enumerate() {
+            let base = shoff + 0x28 * i;
+            out.resize(base + 0x28, 0);
+            put32(&mut out, base + 0x04, *sh_type);
+            put32(&mut out, base + 0x08, *flags);
+            put32(&mut out, base + 0x0C, segments[*segment].1.wrapping_add(0x1000_0000) + offset);
+            put32(&mut out, base + 0x10, offsets[*segment] + offset);
+            put32(&mut out, base + 0x14, *size);
+        }
+        out
+    }
+
+    #[test]
+    fn program_headers_only() {
+        for is_le in [true, false] {
+            let data = elf32(is_le, 0x0800_0101, &[
+                (PT_LOAD, 0x0800_0000, &[1, 2, 3, 4]),
+                (PT_LOAD, 0x0800_0004, &[5, 6]),
+                (PT_LOAD, 0x0800_1000, &[7]),
+                // 不是PT_LOAD或者没有文件内容的段不烧录
+                (4, 0x0800_2000, &[8]),
+                (PT_LOAD, 0x2000_0000, &[]),
+            ], &[]);
+            let firmware = elf_to_bin(&data).unwrap();
+            assert_eq!(segments(&firmware), vec![
+                (0x0800_0000, vec![1, 2, 3, 4, 5, 6]),
+                (0x0800_1000, vec![7]),
+            ]);
+            assert_eq!(firmware.start_address, Some(0x0800_0101));
+            assert_eq!(firmware.go_address(), Some(0x0800_0000));
+        }
+    }
+
+    #[test]
+    fn sections() {
+        let data = elf32(true, 0x0800_0001, &[
+            (PT_LOAD, 0x0800_0000, &[1, 2, 0xFF, 0xFF, 5, 6, 7, 8]),
+        ], &[
+            (SHT_PROGBITS, SHF_ALLOC as u32, 0, 0, 2),
+            (SHT_PROGBITS, SHF_ALLOC as u32, 0, 4, 4),
+            (SHT_PROGBITS, 0, 0, 6, 2),
+        ]);
+        // 整个段按物理地址放置，保留节之间对齐用的填充
+        let firmware = elf_to_bin(&data).unwrap();
+        assert_eq!(segments(&firmware), vec![
+            (0x0800_0000, vec![1, 2, 0xFF, 0xFF, 5, 6, 7, 8]),
+        ]);
+
+        // 段的文件内容覆盖到.bss时，从.bss开始截断
+        let data = elf32(true, 0x0800_0001, &[
+            (PT_LOAD, 0x0800_0000, &[1, 2, 3, 4, 5, 6, 7, 8]),
+        ], &[
+            (SHT_PROGBITS, SHF_ALLOC as u32, 0, 0, 4),
+            (SHT_NOBITS, SHF_ALLOC as u32, 0, 4, 4),
+        ]);
+        let firmware = elf_to_bin(&data).unwrap();
+        assert_eq!(segments(&firmware), vec![
+            (0x0800_0000, vec![1, 2, 3, 4]),
+        ]);
+    }
+
+    /// 生成只有ELF头的64位文件，用来构造头表偏移溢出的情况
+    fn elf64_header(phoff: u64, phnum: u16, shoff: u64, shnum: u16) -> Vec<u8> {
+        let mut out = vec![0u8; 0x40];
+        out[0..4].copy_from_slice(b"\x7FELF");
+        out[4] = 2;
+        out[5] = 1;
+        out[6] = 1;
+        out[0x20..0x28].copy_from_slice(&phoff.to_le_bytes());
+        out[0x28..0x30].copy_from_slice(&shoff.to_le_bytes());
+        out[0x36..0x38].copy_from_slice(&0x38u16.to_le_bytes());
+        out[0x38..0x3A].copy_from_slice(&phnum.to_le_bytes());
+        out[0x3A..0x3C].copy_from_slice(&0x40u16.to_le_bytes());
+        out[0x3C..0x3E].copy_from_slice(&shnum.to_le_bytes());
+        out
+    }
+
+    #[test]
+    fn errors() {
+        let valid = elf32(true, 0, &[(PT_LOAD, 0x0800_0000, &[1, 2, 3, 4])], &[]);
+        let with = |offset: usize, value: u8| {
+            let mut data = valid.clone();
+            data[offset] = value;
+            data
+        };
+        let cases: &[(&str, Vec<u8>)] = &[
+            ("empty", vec![]),
+            ("not elf", vec![0; 0x40]),
+            ("too short", valid[..0x20].to_vec()),
+            ("unknown class", with(4, 3)),
+            ("unknown encoding", with(5, 0)),
+            ("truncated", valid[..valid.len() - 1].to_vec()),
+            ("no loadable segment", elf32(true, 0, &[(4, 0x0800_0000, &[1])], &[])),
+            ("program header overflow", elf64_header(u64::MAX, 2, 0, 0)),
+            ("section header overflow", elf64_header(0, 0, u64::MAX, 2)),
+        ];
+        for (name, data) in cases {
+            assert!(elf_to_bin(data).is_err(), "{}", name);
+        }
+    }
+}
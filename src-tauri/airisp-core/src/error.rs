@@ -0,0 +1,156 @@
+use std::fmt;
+use rust_i18n::t;
+
+#[repr(i32)]
+pub enum ExitCode {
+    Success = 0,
+    PpError = 2, // 外设相关错误
+    FileError = 3, // 文件相关错误
+    NoMatchChip = 4, //没有匹配的芯片 
+    Nack = 5, // 设备返回NACK
+    Timeout = 6, // 等待应答超时
+    ProtocolDesync = 7, // 通讯错位
+    ConnectError = 8, // 无法进入bootloader
+    PortError = 9, // 没有串口或打开串口失败
+    VerifyError = 10, // 校验失败
+    InvalidInput = 11, // 参数错误
+    IoError = 12, // 其他IO错误
+    GangError = 13, // gang 模式下有设备烧录失败
+}
+
+/// AirISP 中所有操作返回的错误类型
+#[derive(Debug)]
+pub enum Error {
+    /// 设备返回了 NACK
+    Nack,
+    /// 等待设备应答超时
+    Timeout,
+    /// 设备的应答不是 ACK 也不是 NACK，通讯已经错位
+    ProtocolDesync(String),
+    /// 多次尝试后仍然无法进入 bootloader
+    Connect,
+    /// 没有找到可用的串口
+    NoPort,
+    /// 打开串口失败
+    PortOpen { port: String, source: serialport::Error },
+    /// 没有找到匹配的芯片
+    ChipMismatch(String),
+    /// 固件文件格式错误
+    FileFormat(String),
+    /// 校验失败，记录第一个不一致的地址和不一致的字节数
+    Verify { address: u32, count: usize },
+    /// 命令行参数或输入数据不合法
+    InvalidInput(String),
+    /// 当前外设不支持该操作
+    Unsupported(String),
+    /// 调试器相关的错误
+    Probe(String),
+    Io(std::io::Error),
+    /// gang 模式下部分设备烧录失败
+    Gang { failed: usize, total: usize },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /// 错误到退出码的唯一映射
+    pub fn exit_code(&self) -> ExitCode {
+        match self {
+            Error::Nack => ExitCode::Nack,
+            Error::Timeout => ExitCode::Timeout,
+            Error::ProtocolDesync(_) => ExitCode::ProtocolDesync,
+            Error::Connect => ExitCode::ConnectError,
+            Error::NoPort | Error::PortOpen { .. } => ExitCode::PortError,
+            Error::ChipMismatch(_) => ExitCode::NoMatchChip,
+            Error::FileFormat(_) => ExitCode::FileError,
+            Error::Verify { .. } => ExitCode::VerifyError,
+            Error::InvalidInput(_) => ExitCode::InvalidInput,
+            Error::Unsupported(_) | Error::Probe(_) => ExitCode::PpError,
+            Error::Io(_) => ExitCode::IoError,
+            Error::Gang { .. } => ExitCode::GangError,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let msg = match self {
+            Error::Nack => t!("error_nack_help"),
+            Error::Timeout => t!("error_timeout_help"),
+            Error::ProtocolDesync(msg) => t!("error_protocol_desync_help", "msg" => msg),
+            Error::Connect => t!("connect_fail_help"),
+            Error::NoPort => t!("no_serial_port_help"),
+            Error::PortOpen { port, source } => t!("open_serial_fail_help", "TTY" => port, "error" => source),
+            Error::ChipMismatch(msg) => t!("error_chip_mismatch_help", "msg" => msg),
+            Error::FileFormat(msg) => t!("error_file_format_help", "msg" => msg),
+            Error::Verify { address, count } => t!("verify_fail_help",
+                "addr" => format!("{:#010x}", address),
+                "count" => format!("{}", count)
+            ),
+            Error::InvalidInput(msg) => t!("error_invalid_input_help", "msg" => msg),
+            Error::Unsupported(msg) => t!("error_unsupported_help", "msg" => msg),
+            Error::Probe(msg) => t!("error_probe_help", "msg" => msg),
+            Error::Io(e) => t!("error_io_help", "msg" => e),
+            Error::Gang { failed, total } => t!("error_gang_help",
+                "failed" => format!("{}", failed),
+                "total" => format!("{}", total)
+            ),
+        };
+        write!(f, "{}", msg)
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::PortOpen { source, .. } => Some(source),
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        match e.kind() {
+            std::io::ErrorKind::TimedOut => Error::Timeout,
+            _ => Error::Io(e),
+        }
+    }
+}
+
+impl From<serialport::Error> for Error {
+    fn from(e: serialport::Error) -> Self {
+        Error::from(std::io::Error::from(e))
+    }
+}
+
+impl From<std::num::ParseIntError> for Error {
+    fn from(e: std::num::ParseIntError) -> Self {
+        Error::InvalidInput(e.to_string())
+    }
+}
+
+impl From<std::string::FromUtf8Error> for Error {
+    fn from(e: std::string::FromUtf8Error) -> Self {
+        Error::FileFormat(e.to_string())
+    }
+}
+
+impl From<probe_rs::Error> for Error {
+    fn from(e: probe_rs::Error) -> Self {
+        Error::Probe(e.to_string())
+    }
+}
+
+impl From<probe_rs::probe::DebugProbeError> for Error {
+    fn from(e: probe_rs::probe::DebugProbeError) -> Self {
+        Error::Probe(e.to_string())
+    }
+}
+
+impl From<probe_rs::flashing::FlashError> for Error {
+    fn from(e: probe_rs::flashing::FlashError) -> Self {
+        Error::Probe(e.to_string())
+    }
+}
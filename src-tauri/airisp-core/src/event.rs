@@ -0,0 +1,29 @@
+use serde::Serialize;
+use crate::peripheral::general_uart::PortInfo;
+use crate::peripheral::swd::ProbeInfo;
+
+/// `--format json` 时输出的事件，每个事件占一行
+#[derive(Serialize, Clone)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    /// 尝试连接设备，attempt 从1开始
+    ConnectAttempt { port: String, attempt: u32, max: u32 },
+    /// 识别到芯片型号
+    ChipDetected { chip: String, pid: u16 },
+    /// 开始写入一段数据
+    SegmentStart { address: u32, size: usize },
+    /// 读写进度
+    Progress { done: usize, total: usize },
+    /// 一段数据写入完成
+    SegmentDone { address: u32, size: usize },
+    /// 一段数据的校验结果，first_mismatch 为第一个不一致的地址
+    VerifyResult { address: u32, size: usize, ok: bool, first_mismatch: Option<u32>, mismatches: usize },
+    /// gang 模式下某个设备的读写进度
+    DeviceProgress { port: String, done: usize, total: usize },
+    /// gang 模式下某个设备的烧录结果，失败时 code 与单独烧录时的退出码相同
+    DeviceResult { port: String, ok: bool, code: i32, message: String, elapsed_ms: u64 },
+    /// list_ports 列出的串口和调试器
+    Ports { ports: Vec<PortInfo>, probes: Vec<ProbeInfo> },
+    /// 操作失败，code 与进程退出码相同
+    Error { code: i32, message: String },
+}
@@ -0,0 +1,205 @@
+use std::collections::BTreeMap;
+use colored::Color;
+use rust_i18n::t;
+use std::sync::Arc;
+use crate::config::Config;
+use crate::error::Error;
+use crate::event::Event;
+use crate::log::LOG;
+use crate::progress::{Progress, ProgressSink};
+use crate::{hex_to_bin, peripheral, validate};
+
+/// 烧录时的选项
+#[derive(Clone)]
+pub struct WriteOptions {
+    /// 烧录前擦除整个Flash
+    pub erase_all: bool,
+    /// 烧录前只擦除文件覆盖到的页
+    pub erase_pages: bool,
+    /// 只擦除和写入有变化的页
+    pub diff: bool,
+    /// 写入后读回校验
+    pub verify: bool,
+    /// 读写进度的接收者
+    pub progress: Arc<dyn ProgressSink>,
+}
+
+impl Default for WriteOptions {
+    fn default() -> Self {
+        WriteOptions {
+            erase_all: false,
+            erase_pages: false,
+            diff: false,
+            verify: true,
+            progress: Progress::Percent.sink(),
+        }
+    }
+}
+
+/// 烧录多个文件，images 为地址和文件路径对，地址只对没有地址信息的文件（例如bin）生效
+pub fn write_flash(config: &Config, images: &[(u32, String)], options: &WriteOptions) -> Result<(), Error>
+{
+    let mut binding = config.get_peripheral_handle()?;
+    let p = binding.get_pp();
+
+    p.reset_bootloader()?;
+
+    // 识别出芯片后再读取所有文件，UF2文件只保留该芯片系列的块，检查通过后再进行擦除和烧录
+    let family_id = p.get_chip_info()?.uf2_family_id;
+    let mut vec_bin = Vec::new();
+    let mut go_address = None;
+    for (address, file_path) in images.iter() {
+        let mut firmware = config.read_file(file_path.as_str(), Some(family_id))?;
+        if let Some(start_address) = firmware.start_address {
+            LOG.info(t!("start_address_help", "addr" => format!("{:#010x}", start_address)).as_str(), Color::BrightBlue);
+        }
+        for bin in firmware.bins.iter_mut() {
+            if bin.address == 0xFFFFFFFF {
+                bin.address = *address; // 0xFFFFFFFF 代表不指定地址，使用 images 中指定的地址
+            }
+        }
+        // 跳转地址取第一个文件的
+        if go_address.is_none() {
+            go_address = firmware.go_address();
+        }
+        vec_bin.extend(firmware.bins);
+    }
+
+    // 在擦除和烧录之前检查所有数据段
+    let flash_size = p.get_flash_size()?;
+    let info = p.get_chip_info()?.clone();
+    validate::validate(&vec_bin, &info, flash_size)?;
+    // 所有格式的文件都在这里统一按4字节对齐，空隙用0xFF填充，写入指令要求地址和长度4字节对齐
+    let vec_bin = hex_to_bin::align(&vec_bin, 4);
+
+    if options.erase_all {
+        p.erase_all()?;
+    }
+
+    if options.erase_pages {
+        // 只擦除文件覆盖到的页，保留其他页的数据
+        for bin in vec_bin.iter() {
+            p.erase_pages(bin.address, bin.data.len() as u32)?;
+        }
+    }
+
+    // 增量烧录时只写入有变化的页
+    let write_bin = if options.diff {
+        diff(p, &vec_bin, &info, &options.progress)?
+    } else {
+        vec_bin.clone()
+    };
+
+    for bin in write_bin.iter() {
+        LOG.event(&Event::SegmentStart { address: bin.address, size: bin.data.len() });
+        p.write_flash(bin.address, &bin.data, &options.progress)?;
+        LOG.event(&Event::SegmentDone { address: bin.address, size: bin.data.len() });
+    }
+
+    if options.verify {
+        for bin in vec_bin.iter() {
+            verify(p, bin, &options.progress)?;
+        }
+    }
+
+    match go_address {
+        // 跳转到入口地址所在的那一段数据
+        Some(address) if config.get_after() == "go" => p.go(address)?,
+        _ => p.reset_app()?,
+    }
+    Ok(())
+}
+
+/// 读回每一段覆盖到的页并与要写入的数据比较，擦除有变化的页，返回需要重新写入的整页数据
+fn diff(p: &mut dyn peripheral::Pp, vec_bin: &[hex_to_bin::Bin], info: &peripheral::ChipInfo, progress: &Arc<dyn ProgressSink>) -> Result<Vec<hex_to_bin::Bin>, Error>
+{
+    let page_size = info.page_size as usize;
+    // 页号 -> (Flash中原有的数据, 要写入的数据)
+    let mut pages: BTreeMap<u32, (Vec<u8>, Vec<u8>)> = BTreeMap::new();
+
+    for bin in vec_bin.iter() {
+        let range = info.pages(bin.address, bin.data.len() as u32)?;
+        let start = info.flash_addr + range.start * info.page_size;
+        let data = p.read_flash(start, range.len() as u32 * info.page_size, progress)?;
+        for (i, page) in range.enumerate() {
+            // 多个段落在同一页时，只保留第一次读回的数据
+            let old = data[i * page_size..(i + 1) * page_size].to_vec();
+            pages.entry(page).or_insert_with(|| (old.clone(), old));
+        }
+
+        // 把新数据覆盖到读回的数据上，未覆盖的部分保持原样
+        for (i, byte) in bin.data.iter().enumerate() {
+            let offset = (bin.address - info.flash_addr) as usize + i;
+            let page = (offset / page_size) as u32;
+            pages.get_mut(&page).unwrap().1[offset % page_size] = *byte;
+        }
+    }
+
+    let mut changed: Vec<hex_to_bin::Bin> = Vec::new();
+    for (page, (old, new)) in pages.iter() {
+        if old == new {
+            continue;
+        }
+        let address = info.flash_addr + page * info.page_size;
+        // 相邻的页合并成一段
+        let adjacent = match changed.last() {
+            Some(last) => last.address + last.data.len() as u32 == address,
+            None => false,
+        };
+        if adjacent {
+            changed.last_mut().unwrap().data.extend_from_slice(new);
+        } else {
+            changed.push(hex_to_bin::Bin {
+                address,
+                data: new.clone(),
+            });
+        }
+    }
+
+    let changed_pages: usize = changed.iter().map(|bin| bin.data.len() / page_size).sum();
+    LOG.info(t!("diff_result_help",
+        "changed" => format!("{}", changed_pages),
+        "total" => format!("{}", pages.len())
+    ).as_str(), Color::BrightBlue);
+
+    for bin in changed.iter() {
+        p.erase_pages(bin.address, bin.data.len() as u32)?;
+    }
+    Ok(changed)
+}
+
+/// 读回数据并与写入的数据进行比较
+fn verify(p: &mut dyn peripheral::Pp, bin: &hex_to_bin::Bin, progress: &Arc<dyn ProgressSink>) -> Result<(), Error>
+{
+    LOG.info(t!("verify_help", "addr" => format!("{:#010x}", bin.address)).as_str(), Color::BrightBlue);
+    let read_back = p.read_flash(bin.address, bin.data.len() as u32, progress)?;
+
+    let mut first_mismatch = None;
+    let mut bad_bytes = 0;
+    for (i, (expect, actual)) in bin.data.iter().zip(read_back.iter()).enumerate() {
+        if expect != actual {
+            if first_mismatch.is_none() {
+                first_mismatch = Some(bin.address + i as u32);
+            }
+            bad_bytes += 1;
+        }
+    }
+    LOG.event(&Event::VerifyResult {
+        address: bin.address,
+        size: bin.data.len(),
+        ok: first_mismatch.is_none(),
+        first_mismatch,
+        mismatches: bad_bytes,
+    });
+
+    match first_mismatch {
+        None => {
+            LOG.info(t!("verify_success_help",
+                "addr" => format!("{:#010x}", bin.address),
+                "size" => format!("{}", bin.data.len())
+            ).as_str(), Color::Green);
+            Ok(())
+        }
+        Some(address) => Err(Error::Verify { address, count: bad_bytes }),
+    }
+}
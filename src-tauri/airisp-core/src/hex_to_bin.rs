@@ -0,0 +1,402 @@
+use crate::error::Error;
+
+#[derive(Clone)]
+pub struct Bin {
+    pub address: u32,
+    pub data: Vec<u8>,
+}
+
+/// 解析后的固件，包含若干段数据和可选的起始地址
+pub struct Firmware {
+    pub bins: Vec<Bin>,
+    /// 文件中记录的程序入口地址（Intel HEX 的 03/05 记录）
+    pub start_address: Option<u32>,
+}
+
+impl Firmware {
+    /// Go指令需要的是向量表地址而不是入口地址，因此取包含入口地址的那一段的起始地址，
+    /// 没有入口地址时使用第一段的起始地址
+    pub fn go_address(&self) -> Option<u32> {
+        if let Some(start) = self.start_address {
+            for bin in self.bins.iter() {
+                if start >= bin.address && (start as u64) < bin.address as u64 + bin.data.len() as u64 {
+                    return Some(bin.address);
+                }
+            }
+        }
+        self.bins.first().map(|bin| bin.address)
+    }
+}
+
+fn format_error(line: usize, msg: &str) -> Error {
+    Error::FileFormat(format!("line {}: {}", line, msg))
+}
+
+fn check(record: &[u8]) -> bool {
+    let mut sum: u8 = 0;
+    for byte in record {
+        sum = sum.wrapping_add(*byte);
+    }
+    sum == 0
+}
+
+/// 按地址排序，并把首尾相接的段合并成一段
+pub fn merge(mut vec_bin: Vec<Bin>) -> Vec<Bin> {
+    vec_bin.retain(|bin| !bin.data.is_empty());
+    vec_bin.sort_by_key(|bin| bin.address);
+    let mut merged: Vec<Bin> = Vec::new();
+    for bin in vec_bin {
+        match merged.last_mut() {
+            Some(last) if last.address as u64 + last.data.len() as u64 == bin.address as u64 => {
+                last.data.extend_from_slice(&bin.data);
+            }
+            _ => merged.push(bin),
+        }
+    }
+    merged
+}
+
+/// 检查各段数据之间是否有重叠
+pub fn check_overlap(vec_bin: &[Bin]) -> Result<(), Error> {
+    let mut sorted = vec_bin.iter().collect::<Vec<&Bin>>();
+    sorted.sort_by_key(|bin| bin.address);
+    for pair in sorted.windows(2) {
+        let end = pair[0].address as u64 + pair[0].data.len() as u64;
+        if end > pair[1].address as u64 {
+            return Err(Error::InvalidInput(format!(
+                    "segment {:#010x}..{:#010x} overlaps segment {:#010x}..{:#010x}",
+                    pair[0].address, end,
+                    pair[1].address, pair[1].address as u64 + pair[1].data.len() as u64
+                )));
+        }
+    }
+    Ok(())
+}
+
+/// 把每一段的起止地址扩展到 align 字节对齐，扩展出的部分填充0xFF，
+/// 扩展后落在同一个对齐单元内的段合并成一段，各段之间不能重叠
+pub fn align(vec_bin: &[Bin], align: u32) -> Vec<Bin> {
+    let mut sorted = vec_bin.iter().filter(|bin| !bin.data.is_empty()).collect::<Vec<&Bin>>();
+    sorted.sort_by_key(|bin| bin.address);
+    let mut aligned: Vec<Bin> = Vec::new();
+    for bin in sorted {
+        let start = bin.address - bin.address % align;
+        let end = (bin.address as u64 + bin.data.len() as u64).next_multiple_of(align as u64);
+        // 与上一段相接或者落在同一个对齐单元内时接在上一段后面，否则新开一段
+        let last = match aligned.last_mut() {
+            Some(last) if last.address as u64 + last.data.len() as u64 >= start as u64 => last,
+            _ => {
+                aligned.push(Bin { address: start, data: Vec::new() });
+                aligned.last_mut().unwrap()
+            }
+        };
+        let offset = (bin.address - last.address) as usize;
+        let len = (end - last.address as u64) as usize;
+        if last.data.len() < len {
+            last.data.resize(len, 0xFF);
+        }
+        last.data[offset..offset + bin.data.len()].copy_from_slice(&bin.data);
+    }
+    aligned
+}
+
+pub fn hex_to_bin(hex: &str) -> Result<Firmware, Error> {
+    let mut vec_bin: Vec<Bin> = Vec::new();
+    let mut start_address = None;
+
+    // 扩展段地址(02)和扩展线性地址(04)记录给出的基地址
+    let mut base_address: u32 = 0;
+
+    let mut bin = Bin {
+        address: 0,
+        data: Vec::new(),
+    };
+
+    let mut is_end = false;
+
+    for (index, line) in hex.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if is_end {
+            return Err(format_error(line_number, "data after end of file record"));
+        }
+        if !line.starts_with(':') {
+            return Err(format_error(line_number, "record does not start with ':'"));
+        }
+        let record = hex::decode(&line[1..])
+            .map_err(|e| format_error(line_number, &format!("invalid hex digits: {}", e)))?;
+        // 长度、地址、类型和校验至少5个字节
+        if record.len() < 5 {
+            return Err(format_error(line_number, "record is too short"));
+        }
+        let data_length = record[0] as usize;
+        if record.len() != data_length + 5 {
+            return Err(format_error(line_number, &format!(
+                "record length {} does not match byte count {}", record.len() - 5, data_length)));
+        }
+        if !check(&record) {
+            return Err(format_error(line_number, "checksum error"));
+        }
+        let offset_address = u16::from_be_bytes([record[1], record[2]]) as u32;
+        let record_type = record[3];
+        let data = &record[4..4 + data_length];
+
+        match record_type {
+            0 => { // 数据记录
+                let address = base_address.wrapping_add(offset_address);
+                // 地址不连续时开始新的一段
+                if bin.address as u64 + bin.data.len() as u64 != address as u64 {
+                    if !bin.data.is_empty() {
+                        vec_bin.push(bin.clone());
+                    }
+                    bin.address = address;
+                    bin.data.clear();
+                }
+                bin.data.extend_from_slice(data);
+            }
+            1 => { // 文件结束记录
+                is_end = true;
+            }
+            2 => { // 扩展段地址记录，段地址左移4位
+                if data_length != 2 {
+                    return Err(format_error(line_number, "extended segment address record must have 2 data bytes"));
+                }
+                base_address = (u16::from_be_bytes([data[0], data[1]]) as u32) << 4;
+            }
+            3 => { // 起始段地址记录，CS:IP
+                if data_length != 4 {
+                    return Err(format_error(line_number, "start segment address record must have 4 data bytes"));
+                }
+                let cs = u16::from_be_bytes([data[0], data[1]]) as u32;
+                let ip = u16::from_be_bytes([data[2], data[3]]) as u32;
+                start_address = Some((cs << 4).wrapping_add(ip));
+            }
+            4 => { // 扩展线性地址记录，高16位地址
+                if data_length != 2 {
+                    return Err(format_error(line_number, "extended linear address record must have 2 data bytes"));
+                }
+                base_address = (u16::from_be_bytes([data[0], data[1]]) as u32) << 16;
+            }
+            5 => { // 起始线性地址记录
+                if data_length != 4 {
+                    return Err(format_error(line_number, "start linear address record must have 4 data bytes"));
+                }
+                start_address = Some(u32::from_be_bytes([data[0], data[1], data[2], data[3]]));
+            }
+            _ => {
+                return Err(format_error(line_number, &format!("unknown record type {:02X}", record_type)));
+            }
+        }
+    }
+    if !is_end {
+        return Err(format_error(hex.lines().count(), "missing end of file record"));
+    }
+    vec_bin.push(bin);
+
+    Ok(Firmware {
+        bins: merge(vec_bin),
+        start_address,
+    })
+}
+
+fn hex_record(record_type: u8, offset_address: u16, data: &[u8]) -> String {
+    let mut record = vec![data.len() as u8, (offset_address >> 8) as u8, offset_address as u8, record_type];
+    record.extend_from_slice(data);
+    let mut sum: u8 = 0;
+    for byte in record.iter() {
+        sum = sum.wrapping_add(*byte);
+    }
+    record.push((0xFF - sum).wrapping_add(1));
+    format!(":{}\n", hex::encode_upper(record))
+}
+
+pub fn bin_to_hex(vec_bin: &[Bin]) -> String {
+    let mut hex = String::new();
+    let mut high_address: Option<u16> = None;
+
+    for bin in vec_bin {
+        // 每行最多16个字节，且不跨越64K边界
+        let mut i = 0;
+        while i < bin.data.len() {
+            let address = bin.address.wrapping_add(i as u32);
+            if high_address != Some((address >> 16) as u16) {
+                // 扩展线性地址记录
+                high_address = Some((address >> 16) as u16);
+                hex.push_str(&hex_record(4, 0, &((address >> 16) as u16).to_be_bytes()));
+            }
+            let remain_in_block = 0x10000 - (address & 0xFFFF) as usize;
+            let len = 16.min(bin.data.len() - i).min(remain_in_block);
+            hex.push_str(&hex_record(0, address as u16, &bin.data[i..i + len]));
+            i += len;
+        }
+    }
+    // 文件结束记录
+    hex.push_str(&hex_record(1, 0, &[]));
+    hex
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// 各段的地址和数据，各种固件格式的测试共用
+    pub(crate) type Segments = Vec<(u32, Vec<u8>)>;
+
+    /// 取出解析结果中各段的地址和数据，方便和预期的结果比较
+    pub(crate) fn segments(firmware: &Firmware) -> Segments {
+        firmware.bins.iter().map(|bin| (bin.address, bin.data.clone())).collect()
+    }
+
+    /// 生成一个完整的 hex 文件，records 为 (类型, 地址, 数据)
+    fn hex(records: &[(u8, u16, &[u8])]) -> String {
+        records.iter().map(|(record_type, address, data)| hex_record(*record_type, *address, data)).collect()
+    }
+
+    #[test]
+    fn hex_to_bin_ok() {
+        // (文件内容, 各段的地址和数据, 起始地址)
+        let cases: Vec<(String, Segments, Option<u32>)> = vec![
+            (hex(&[(0, 0x0000, &[1, 2, 3]), (1, 0, &[])]), vec![(0x0000, vec![1, 2, 3])], None),
+            // 连续的数据记录合并成一段
+            (hex(&[(0, 0x0000, &[1, 2]), (0, 0x0002, &[3]), (1, 0, &[])]), vec![(0x0000, vec![1, 2, 3])], None),
+            // 不连续时分成两段
+            (hex(&[(0, 0x0000, &[1]), (0, 0x0010, &[2]), (1, 0, &[])]), vec![(0x0000, vec![1]), (0x0010, vec![2])], None),
+            // 04 扩展线性地址
+            (hex(&[(4, 0, &[0x08, 0x00]), (0, 0x0100, &[0xAA]), (1, 0, &[])]), vec![(0x0800_0100, vec![0xAA])], None),
+            // 02 扩展段地址，左移4位
+            (hex(&[(2, 0, &[0x10, 0x00]), (0, 0x0004, &[0xBB]), (1, 0, &[])]), vec![(0x0001_0004, vec![0xBB])], None),
+            // 跨越64K边界的数据仍然是一段
+            (
+                hex(&[(4, 0, &[0x08, 0x00]), (0, 0xFFFF, &[1]), (4, 0, &[0x08, 0x01]), (0, 0x0000, &[2]), (1, 0, &[])]),
+                vec![(0x0800_FFFF, vec![1, 2])],
+                None,
+            ),
+            // 05 起始线性地址
+            (
+                hex(&[(0, 0x0000, &[1]), (5, 0, &[0x08, 0x00, 0x01, 0x01]), (1, 0, &[])]),
+                vec![(0x0000, vec![1])],
+                Some(0x0800_0101),
+            ),
+            // 03 起始段地址 CS:IP
+            (
+                hex(&[(0, 0x0000, &[1]), (3, 0, &[0x10, 0x00, 0x00, 0x20]), (1, 0, &[])]),
+                vec![(0x0000, vec![1])],
+                Some(0x0001_0020),
+            ),
+            // 小写、空行和行尾空白
+            (":0100000001FE\n\n  :00000001ff  \n".to_string(), vec![(0x0000, vec![1])], None),
+        ];
+        for (text, expected, start_address) in cases.iter() {
+            let firmware = hex_to_bin(text).unwrap();
+            assert_eq!(segments(&firmware), *expected, "{}", text);
+            assert_eq!(firmware.start_address, *start_address, "{}", text);
+        }
+    }
+
+    #[test]
+    fn hex_to_bin_err() {
+        let cases: &[String] = &[
+            // 缺少文件结束记录
+            hex(&[(0, 0x0000, &[1])]),
+            String::new(),
+            // 文件结束后还有数据
+            hex(&[(1, 0, &[]), (0, 0x0000, &[1])]),
+            // 校验错误
+            ":0100000001FF\n:00000001FF\n".to_string(),
+            // 没有冒号
+            "0100000001FE\n:00000001FF\n".to_string(),
+            // 非法字符
+            ":01000000ZZFE\n:00000001FF\n".to_string(),
+            // 记录太短
+            ":0000\n:00000001FF\n".to_string(),
+            // 长度和数据不一致
+            ":0200000001FD\n:00000001FF\n".to_string(),
+            // 未知的记录类型
+            hex(&[(6, 0, &[]), (1, 0, &[])]),
+            // 扩展地址和起始地址记录的长度不对
+            hex(&[(2, 0, &[0x10]), (1, 0, &[])]),
+            hex(&[(3, 0, &[0x10, 0x00]), (1, 0, &[])]),
+            hex(&[(4, 0, &[0x08, 0x00, 0x00]), (1, 0, &[])]),
+            hex(&[(5, 0, &[0x08, 0x00]), (1, 0, &[])]),
+        ];
+        for text in cases {
+            assert!(hex_to_bin(text).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn bin_to_hex_round_trip() {
+        let bins = vec![
+            Bin { address: 0x0800_FFF8, data: (0..40).collect() },
+            Bin { address: 0x2000_0000, data: vec![0x55; 3] },
+        ];
+        let firmware = hex_to_bin(&bin_to_hex(&bins)).unwrap();
+        let expected = bins.iter().map(|bin| (bin.address, bin.data.clone())).collect::<Segments>();
+        assert_eq!(segments(&firmware), expected);
+    }
+
+    #[test]
+    fn go_address() {
+        let bins = vec![
+            Bin { address: 0x0800_0000, data: vec![0; 0x100] },
+            Bin { address: 0x0800_1000, data: vec![0; 0x100] },
+        ];
+        let cases = [
+            (None, Some(0x0800_0000)),
+            (Some(0x0800_1004), Some(0x0800_1000)),
+            (Some(0x0800_0000), Some(0x0800_0000)),
+            // 入口地址不在任何一段中时使用第一段
+            (Some(0x2000_0000), Some(0x0800_0000)),
+        ];
+        for (start_address, expected) in cases {
+            let firmware = Firmware { bins: bins.clone(), start_address };
+            assert_eq!(firmware.go_address(), expected, "{:?}", start_address);
+        }
+    }
+
+    #[test]
+    fn merge() {
+        let merged = super::merge(vec![
+            Bin { address: 4, data: vec![2] },
+            Bin { address: 0, data: vec![0; 4] },
+            Bin { address: 9, data: vec![] },
+            Bin { address: 16, data: vec![3] },
+        ]);
+        let firmware = Firmware { bins: merged, start_address: None };
+        assert_eq!(segments(&firmware), vec![(0, vec![0, 0, 0, 0, 2]), (16, vec![3])]);
+    }
+
+    #[test]
+    fn align() {
+        let aligned = super::align(&[
+            Bin { address: 0x10, data: vec![4] },
+            Bin { address: 1, data: vec![1, 2] },
+            // 和上一段在同一个字内，填充不能覆盖上一段的数据
+            Bin { address: 3, data: vec![3] },
+            Bin { address: 5, data: vec![] },
+            Bin { address: 0x21, data: vec![5, 6, 7, 8, 9] },
+        ], 4);
+        let firmware = Firmware { bins: aligned, start_address: None };
+        assert_eq!(segments(&firmware), vec![
+            (0, vec![0xFF, 1, 2, 3]),
+            (0x10, vec![4, 0xFF, 0xFF, 0xFF]),
+            (0x20, vec![0xFF, 5, 6, 7, 8, 9, 0xFF, 0xFF]),
+        ]);
+    }
+
+    #[test]
+    fn check_overlap() {
+        let cases: &[(&[Bin], bool)] = &[
+            (&[Bin { address: 0, data: vec![0; 4] }, Bin { address: 4, data: vec![0] }], true),
+            (&[Bin { address: 4, data: vec![0] }, Bin { address: 0, data: vec![0; 5] }], false),
+            (&[Bin { address: 0, data: vec![0; 4] }, Bin { address: 2, data: vec![0] }], false),
+            (&[Bin { address: 0x1000, data: vec![0] }], true),
+        ];
+        for (bins, ok) in cases {
+            assert_eq!(super::check_overlap(bins).is_ok(), *ok);
+        }
+    }
+}
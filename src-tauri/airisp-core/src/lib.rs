@@ -0,0 +1,21 @@
+//! AirISP 的烧录核心：外设（串口bootloader和SWD）、固件文件解析和芯片信息
+//!
+//! 命令行和图形界面都基于这个库实现，也可以直接在其他 Rust 程序中调用。
+
+rust_i18n::i18n!("i18n");
+
+pub mod baud_cache;
+pub mod config;
+pub mod config_file;
+pub mod error;
+pub mod event;
+pub mod flash;
+pub mod log;
+pub mod peripheral;
+pub mod progress;
+pub mod reset_sequence;
+pub mod hex_to_bin;
+pub mod elf_to_bin;
+pub mod srec_to_bin;
+pub mod uf2;
+pub mod validate;
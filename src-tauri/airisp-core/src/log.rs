@@ -0,0 +1,163 @@
+use colored::{Color, Colorize};
+use lazy_static::lazy_static;
+use std::cell::Cell;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use crate::event::Event;
+
+pub enum Level {
+    Trace,
+    Info,
+    Warn,
+    Error,
+}
+
+/// 输出格式，Json 模式下标准输出只包含事件，每行一个JSON对象，其他文字输出到标准错误
+#[derive(PartialEq, Clone, Copy)]
+pub enum Format {
+    Text,
+    Json,
+}
+
+thread_local! {
+    /// 当前线程的输出是否被屏蔽，例如 gang 模式下每个设备的烧录线程
+    static MUTED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// 接收事件的回调，例如图形界面把事件转发给前端
+pub type Listener = Box<dyn Fn(&Event) + Send + Sync>;
+
+pub struct Log  {
+    level: Level,
+    json: AtomicBool,
+    listener: Mutex<Option<Listener>>,
+}
+
+impl Log {
+    pub fn new(level: Level) -> Log {
+        Log {
+            level,
+            json: AtomicBool::new(false),
+            listener: Mutex::new(None),
+        }
+    }
+
+    pub fn set_format(&self, format: Format) {
+        self.json.store(format == Format::Json, Ordering::SeqCst);
+    }
+
+    pub fn is_json(&self) -> bool {
+        self.json.load(Ordering::SeqCst)
+    }
+
+    /// 屏蔽或恢复当前线程的文字和事件输出，错误信息不受影响
+    pub fn mute_thread(&self, muted: bool) {
+        MUTED.with(|m| m.set(muted));
+    }
+
+    pub fn is_muted(&self) -> bool {
+        MUTED.with(|m| m.get())
+    }
+
+    /// 输出一行文字，Json 模式下输出到标准错误
+    fn line(&self, msg: &str) {
+        if self.is_muted() {
+            return;
+        }
+        if self.is_json() {
+            eprintln!("{}", msg);
+        } else {
+            println!("{}", msg);
+        }
+    }
+
+    /// 输出文字但不换行，用于进度显示
+    pub fn print(&self, msg: &str) {
+        if self.is_muted() {
+            return;
+        }
+        if self.is_json() {
+            eprint!("{}", msg);
+            std::io::stderr().flush().unwrap();
+        } else {
+            print!("{}", msg);
+            std::io::stdout().flush().unwrap();
+        }
+    }
+
+    pub fn set_listener(&self, listener: Option<Listener>) {
+        *self.listener.lock().unwrap() = listener;
+    }
+
+    /// 输出事件，Json 模式下打印到标准输出，设置了回调时同时交给回调处理
+    pub fn event(&self, event: &Event) {
+        if self.is_muted() {
+            return;
+        }
+        if self.is_json() {
+            println!("{}", serde_json::to_string(event).unwrap());
+        }
+        if let Some(listener) = self.listener.lock().unwrap().as_ref() {
+            listener(event);
+        }
+    }
+
+    pub fn info(&self, msg: &str, color: Color) {
+        match self.level {
+            Level::Info | Level::Trace => {
+                self.line(&format!("{}", msg.color(color)));
+            },
+            _ => {},
+        }
+    }
+
+    pub fn info_no_color(&self, msg: &str) {
+        self.info(msg, Color::White);
+    }
+
+    pub fn error(&self, msg: &str) {
+        let level = "ERROR: ".color(Color::Red);
+        let time = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string().color(Color::White);
+        eprintln!("{} [{}] {}",
+                 level,
+                 time,
+                 msg
+        );
+    }
+
+    pub fn warn(&self, msg: &str) {
+        match self.level {
+            Level::Warn | Level::Info | Level::Trace => {
+                let level = "Warn: ".color(Color::Yellow);
+                let time = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string().color(Color::White);
+                self.line(&format!("{} [{}] {}",
+                         level,
+                         time,
+                         msg
+                ));
+            },
+            _ => {},
+        }
+    }
+
+    pub fn trace(&self, msg: &str) {
+        if let Level::Trace = self.level {
+            let level = "Trace: ".color(Color::Cyan);
+            let time = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string().color(Color::White);
+            self.line(&format!("{} [{}] {}",
+                     level,
+                     time,
+                     msg
+            ));
+        }
+    }
+
+    pub fn set_level(&mut self, level: Level) {
+        self.level = level;
+    }
+}
+
+lazy_static! {
+    pub static ref LOG: Log = Log::new(Level::Info);
+}
@@ -0,0 +1,1071 @@
+use crate::peripheral;
+use crate::peripheral::Pp;
+use crate::config;
+use colored::Color;
+use rust_i18n::t;
+use serde::Serialize;
+use serialport::SerialPort;
+use crate::error::Error;
+use std::io::{Read, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::runtime::Runtime;
+use crate::event::Event;
+use crate::baud_cache;
+use crate::log::LOG;
+use crate::reset_sequence::ResetSequence;
+use crate::progress::{Operation, ProgressSink};
+
+use super::{chip_info, CHIPS};
+
+// 完整列出 bootloader 的指令，部分指令暂未使用
+#[allow(dead_code)]
+#[repr(u8)]
+#[derive(Clone, Copy, PartialEq)]
+enum Command {
+    Get = 0x00,
+    GetVersion = 0x01,
+    GetID = 0x02,
+    GetDeviceID = 0x03,
+    ReadMemory = 0x11,
+    Go = 0x21,
+    WriteMemory = 0x31,
+    Erase = 0x43,
+    ExtendedErase = 0x44,
+    WriteProtect = 0x63,
+    WriteUnprotect = 0x73,
+    ReadProtect = 0x82,
+    ReadUnprotect = 0x92,
+}
+
+#[repr(u8)]
+enum Ack {
+    Ack = 0x79,
+    Nack = 0x1F,
+}
+
+#[allow(dead_code)]
+#[repr(u16)]
+enum ExtendedErase {
+    EraseAll = 0xFFFF,
+    Storage0 = 0xFFFE,
+    Storage1 = 0xFFFD,
+}
+
+impl Command {
+    fn name(code: u8) -> &'static str {
+        match code {
+            0x00 => "Get",
+            0x01 => "GetVersion",
+            0x02 => "GetID",
+            0x03 => "GetDeviceID",
+            0x11 => "ReadMemory",
+            0x21 => "Go",
+            0x31 => "WriteMemory",
+            0x43 => "Erase",
+            0x44 => "ExtendedErase",
+            0x63 => "WriteProtect",
+            0x73 => "WriteUnprotect",
+            0x82 => "ReadProtect",
+            0x92 => "ReadUnprotect",
+            _ => "Unknown",
+        }
+    }
+}
+
+/// bootloader通过Get和GetVersion指令返回的信息
+pub struct BootloaderInfo {
+    pub version: u8,
+    pub commands: Vec<u8>,
+    pub option_bytes: [u8; 2],
+}
+
+/// 自动波特率依次尝试的波特率，第一个是所有转换器都支持的安全值
+const BAUD_CANDIDATES: &[u32] = &[115200, 230400, 460800, 921600, 1000000];
+
+/// 常见的USB转串口芯片，自动选择串口时按照这个顺序优先尝试
+const KNOWN_ADAPTERS: &[(u16, u16, &str)] = &[
+    (0x1a86, 0x7523, "CH340"),
+    (0x1a86, 0x55d3, "CH343"),
+    (0x1a86, 0x55d4, "CH9102"),
+    (0x10c4, 0xea60, "CP210x"),
+    (0x0403, 0x6001, "FT232"),
+];
+
+/// 串口的类型
+#[derive(Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PortType {
+    Usb,
+    Pci,
+    Bluetooth,
+    Unknown,
+}
+
+/// 系统中的串口，USB串口会带有VID、PID等信息
+#[derive(Serialize, Clone)]
+pub struct PortInfo {
+    pub name: String,
+    pub port_type: PortType,
+    pub vid: Option<u16>,
+    pub pid: Option<u16>,
+    pub serial_number: Option<String>,
+    pub manufacturer: Option<String>,
+    pub product: Option<String>,
+}
+
+impl PortInfo {
+    /// 是否为合宙的调试器或开发板，通过USB描述中的产品名称判断
+    fn is_air_adapter(&self) -> bool {
+        self.product.as_deref().is_some_and(|product| product.starts_with("Air"))
+            || self.manufacturer.as_deref().is_some_and(|manufacturer| manufacturer.contains("LuatOS"))
+    }
+
+    /// 已知的USB转串口芯片名称
+    fn known_adapter(&self) -> Option<&'static str> {
+        KNOWN_ADAPTERS
+            .iter()
+            .find(|(vid, pid, _)| self.vid == Some(*vid) && self.pid == Some(*pid))
+            .map(|(_, _, name)| *name)
+    }
+
+    /// 自动选择串口时的优先级，数字越小越优先，None 表示不尝试
+    fn rank(&self) -> Option<usize> {
+        if self.is_air_adapter() {
+            return Some(0);
+        }
+        if let Some(index) = KNOWN_ADAPTERS.iter().position(|(vid, pid, _)| self.vid == Some(*vid) && self.pid == Some(*pid)) {
+            return Some(1 + index);
+        }
+        match self.port_type {
+            PortType::Usb => Some(1 + KNOWN_ADAPTERS.len()),
+            PortType::Pci | PortType::Unknown => Some(2 + KNOWN_ADAPTERS.len()),
+            // 蓝牙串口打开很慢，也不会是烧录器
+            PortType::Bluetooth => None,
+        }
+    }
+
+    /// 选择这个串口的原因，用于提示用户
+    fn reason(&self) -> String {
+        let id = match (self.vid, self.pid) {
+            (Some(vid), Some(pid)) => format!("{:04x}:{:04x}", vid, pid),
+            _ => String::new(),
+        };
+        if self.is_air_adapter() {
+            format!("{} {}", self.product.as_deref().unwrap_or("Air"), id)
+        } else if let Some(name) = self.known_adapter() {
+            format!("{} {}", name, id)
+        } else {
+            match self.port_type {
+                PortType::Usb => format!("USB {}", id),
+                PortType::Pci => "PCI".to_string(),
+                PortType::Bluetooth => "Bluetooth".to_string(),
+                PortType::Unknown => "unknown".to_string(),
+            }
+        }
+    }
+}
+
+/// 列出系统中所有的串口
+pub fn list_ports() -> Result<Vec<PortInfo>, Error> {
+    let ports = serialport::available_ports()?;
+    Ok(ports
+        .into_iter()
+        .map(|port| {
+            let port_type = match &port.port_type {
+                serialport::SerialPortType::UsbPort(_) => PortType::Usb,
+                serialport::SerialPortType::PciPort => PortType::Pci,
+                serialport::SerialPortType::BluetoothPort => PortType::Bluetooth,
+                serialport::SerialPortType::Unknown => PortType::Unknown,
+            };
+            match port.port_type {
+                serialport::SerialPortType::UsbPort(usb) => PortInfo {
+                    name: port.port_name,
+                    port_type,
+                    vid: Some(usb.vid),
+                    pid: Some(usb.pid),
+                    serial_number: usb.serial_number,
+                    manufacturer: usb.manufacturer,
+                    product: usb.product,
+                },
+                _ => PortInfo {
+                    name: port.port_name,
+                    port_type,
+                    vid: None,
+                    pid: None,
+                    serial_number: None,
+                    manufacturer: None,
+                    product: None,
+                },
+            }
+        })
+        .collect())
+}
+
+/// 计算 address 之后第 offset 个字节的地址，超出32位地址空间时返回错误而不是溢出
+fn offset_address(address: u32, offset: usize) -> Result<u32, Error> {
+    u32::try_from(offset)
+        .ok()
+        .and_then(|offset| address.checked_add(offset))
+        .ok_or_else(|| Error::InvalidInput(format!("address {:#010x} + {:#x} is out of range", address, offset)))
+}
+
+/// 按照自动选择的优先级排列串口，去掉不需要尝试的串口
+pub fn rank_ports(ports: Vec<PortInfo>) -> Vec<PortInfo> {
+    let mut ports = ports
+        .into_iter()
+        .filter_map(|port| port.rank().map(|rank| (rank, port)))
+        .collect::<Vec<_>>();
+    // 稳定排序，同一优先级保持系统返回的顺序
+    ports.sort_by_key(|(rank, _)| *rank);
+    ports.into_iter().map(|(_, port)| port).collect()
+}
+
+pub struct GeneralUart<'a> {
+    air_isp: &'a config::Config,
+
+    handle: Box<dyn SerialPort>,
+
+    info: Option<&'static peripheral::ChipInfo>,
+
+    /// bootloader支持的指令，为None时表示还没有查询过
+    capabilities: Option<Vec<u8>>,
+
+    /// 自动波特率是否已经选好了波特率
+    baud_selected: bool,
+
+    /// 进入bootloader的复位序列
+    before: ResetSequence,
+
+    /// 烧录完成后的复位序列，为None时使用Go指令
+    after: Option<ResetSequence>,
+}
+
+impl GeneralUart<'_> {
+    pub fn new(air_isp: &config::Config) -> Result<GeneralUart<'_>, Error> {
+        let mut speed = air_isp.get_baud();
+        if speed == 0 {
+            speed = 115200; // 默认波特率115200
+        }
+        // 打开串口前先检查复位序列
+        air_isp.get_before_sequence()?;
+        air_isp.get_after_sequence()?;
+        if air_isp.get_port() == "auto" {
+            return GeneralUart::auto_detect(air_isp, speed);
+        }
+        let port_name = air_isp.get_port();
+        let uart = GeneralUart::open(air_isp, &port_name, speed)?;
+        LOG.info(t!("open_serial_success_help", "TTY" => port_name).as_str(), Color::Green);
+        Ok(uart)
+    }
+
+    fn open<'a>(air_isp: &'a config::Config, port_name: &str, speed: u32) -> Result<GeneralUart<'a>, Error> {
+        let before = air_isp.get_before_sequence()?;
+        let after = air_isp.get_after_sequence()?;
+        let port = serialport::new(port_name, speed)
+            .timeout(std::time::Duration::from_millis(2000))
+            .parity(serialport::Parity::Even)
+            .open()
+            .map_err(|e| Error::PortOpen { port: port_name.to_string(), source: e })?;
+
+        Ok(GeneralUart {
+            air_isp,
+            handle: port,
+            info: None,
+            capabilities: None,
+            baud_selected: false,
+            before,
+            after,
+        })
+    }
+
+    /**
+     * 自动选择串口：按照USB VID/PID排序后依次握手，使用第一个回复ACK的串口
+     */
+    fn auto_detect(air_isp: &config::Config, speed: u32) -> Result<GeneralUart<'_>, Error> {
+        let ports = rank_ports(list_ports()?);
+        if ports.is_empty() {
+            return Err(Error::NoPort);
+        }
+
+        let runtime = Runtime::new()?;
+        for port in ports.iter() {
+            LOG.info(t!("auto_port_try_help", "TTY" => &port.name, "reason" => port.reason()).as_str(), Color::White);
+            let mut uart = match GeneralUart::open(air_isp, &port.name, speed) {
+                Ok(uart) => uart,
+                Err(e) => {
+                    LOG.warn(e.to_string().as_str());
+                    continue;
+                }
+            };
+            // 握手时缩短超时时间，没有设备应答的串口不用等太久
+            uart.handle.set_timeout(Duration::from_millis(200))?;
+            let synced = runtime.block_on(uart.sync());
+            uart.handle.set_timeout(Duration::from_millis(2000))?;
+            if synced {
+                LOG.info(t!("auto_port_selected_help", "TTY" => &port.name, "reason" => port.reason()).as_str(), Color::Green);
+                return Ok(uart);
+            }
+        }
+
+        // 没有串口应答时不要随便选一个串口，列出尝试过的串口后返回连接失败
+        let tried = ports.iter().map(|port| port.name.as_str()).collect::<Vec<&str>>().join(", ");
+        LOG.warn(t!("auto_port_none_help", "ports" => tried).as_str());
+        Err(Error::Connect)
+    }
+
+    /**
+     * 发送GetID指令，返回芯片ID的原始字节
+     */
+    fn read_id(&mut self) -> Result<Vec<u8>, Error> {
+        let cmd = [Command::GetID as u8, !(Command::GetID as u8)];
+        self.handle.write_all(&cmd)?;
+        std::thread::sleep(Duration::from_millis(10));
+
+        self.get_ack()?;
+        let mut buf = [0u8; 1]; // 先取出字节数大小
+        self.handle.read_exact(&mut buf)?;
+        let data_len = buf[0] as usize + 1;
+        let mut data_buf = vec![0u8; data_len + 1];
+        self.handle.read_exact(&mut data_buf)?;
+        data_buf.truncate(data_len);
+        Ok(data_buf)
+    }
+
+    /**
+     * 发送ReadMemory指令读取一块数据，长度为1到256字节
+     */
+    fn read_memory(&mut self, address: u32, len: usize) -> Result<Vec<u8>, Error> {
+        let cmd = [Command::ReadMemory as u8, !(Command::ReadMemory as u8)];
+        // 发送指令
+        self.handle.write_all(&cmd)?;
+        self.get_ack()?;
+
+        // 发送地址
+        let mut address_buf = address.to_be_bytes().to_vec();
+        address_buf.push(address_buf[0] ^ address_buf[1] ^ address_buf[2] ^ address_buf[3]);
+        self.handle.write_all(&address_buf)?;
+        self.get_ack()?;
+
+        // 发送读取长度（N-1）和校验
+        let len_buf = [(len - 1) as u8, !((len - 1) as u8)];
+        self.handle.write_all(&len_buf)?;
+        self.get_ack()?;
+
+        // 读取数据
+        let mut data_buf = vec![0u8; len];
+        self.handle.read_exact(&mut data_buf)?;
+        Ok(data_buf)
+    }
+
+    /**
+     * 发送Get指令，返回bootloader版本和支持的指令列表
+     */
+    fn get_commands(&mut self) -> Result<(u8, Vec<u8>), Error> {
+        let cmd = [Command::Get as u8, !(Command::Get as u8)];
+        self.handle.write_all(&cmd)?;
+        self.get_ack()?;
+
+        // 第一个字节是后续字节数减一，然后是bootloader版本和支持的指令
+        let mut buf = [0u8; 1];
+        self.handle.read_exact(&mut buf)?;
+        let mut data_buf = vec![0u8; buf[0] as usize + 1];
+        self.handle.read_exact(&mut data_buf)?;
+        self.get_ack()?;
+
+        let version = data_buf.remove(0);
+        Ok((version, data_buf))
+    }
+
+    /**
+     * 发送GetVersion指令，返回bootloader版本和两个选项字节
+     */
+    fn get_version(&mut self) -> Result<(u8, [u8; 2]), Error> {
+        let cmd = [Command::GetVersion as u8, !(Command::GetVersion as u8)];
+        self.handle.write_all(&cmd)?;
+        self.get_ack()?;
+
+        let mut data_buf = [0u8; 3];
+        self.handle.read_exact(&mut data_buf)?;
+        self.get_ack()?;
+        Ok((data_buf[0], [data_buf[1], data_buf[2]]))
+    }
+
+    /**
+     * 查询bootloader是否支持某条指令，第一次调用时会发送Get指令并缓存结果
+     */
+    fn supports(&mut self, command: Command) -> bool {
+        if self.capabilities.is_none() {
+            match self.get_commands() {
+                Ok((_, commands)) => {
+                    LOG.trace(format!("bootloader commands: {:02x?}", commands).as_str());
+                    self.capabilities = Some(commands);
+                }
+                Err(_) => {
+                    // 查询失败时假设支持所有指令，保持原来的行为
+                    LOG.warn(t!("bootloader_get_fail_help").as_str());
+                    self.handle.clear(serialport::ClearBuffer::All).ok();
+                    self.capabilities = Some(vec![
+                        Command::Get as u8, Command::GetVersion as u8, Command::GetID as u8,
+                        Command::ReadMemory as u8, Command::Go as u8, Command::WriteMemory as u8,
+                        Command::ExtendedErase as u8, Command::WriteProtect as u8,
+                        Command::WriteUnprotect as u8, Command::ReadProtect as u8,
+                        Command::ReadUnprotect as u8,
+                    ]);
+                }
+            }
+        }
+        self.capabilities.as_ref().unwrap().contains(&(command as u8))
+    }
+
+    /**
+     * 擦除全片，根据bootloader支持的指令选择Erase或ExtendedErase
+     */
+    fn mass_erase(&mut self) -> Result<(), Error> {
+        if self.supports(Command::ExtendedErase) {
+            let cmd = [Command::ExtendedErase as u8, !(Command::ExtendedErase as u8)];
+            self.handle.write_all(&cmd)?;
+            self.get_ack()?;
+
+            let mut data_buf = vec![0u8; 3];
+            data_buf[0] = (ExtendedErase::EraseAll as u16 >> 8) as u8;
+            data_buf[1] = ExtendedErase::EraseAll as u16 as u8;
+            data_buf[2] = data_buf[0] - data_buf[1];
+            self.handle.write_all(&data_buf)?;
+        } else if self.supports(Command::Erase) {
+            let cmd = [Command::Erase as u8, !(Command::Erase as u8)];
+            self.handle.write_all(&cmd)?;
+            self.get_ack()?;
+
+            // 0xFF表示擦除全片
+            self.handle.write_all(&[0xFF, 0x00])?;
+        } else {
+            return Err(Error::Unsupported("bootloader does not support erase".to_string()));
+        }
+        self.get_ack()
+    }
+
+    /**
+     * 擦除指定的页，根据bootloader支持的指令选择Erase或ExtendedErase
+     */
+    fn erase_page_list(&mut self, pages: &[u16]) -> Result<(), Error> {
+        if self.supports(Command::ExtendedErase) {
+            self.extended_erase_pages(pages)
+        } else if self.supports(Command::Erase) {
+            // Erase指令的页号只有一个字节
+            if pages.iter().any(|page| *page > 0xFF) {
+                return Err(Error::Unsupported("page number is too large for the erase command".to_string()));
+            }
+            let cmd = [Command::Erase as u8, !(Command::Erase as u8)];
+            self.handle.write_all(&cmd)?;
+            self.get_ack()?;
+
+            let mut data_buf = Vec::with_capacity(pages.len() + 2);
+            data_buf.push((pages.len() - 1) as u8);
+            data_buf.extend(pages.iter().map(|page| *page as u8));
+            let checksum = data_buf.iter().fold(0u8, |sum, byte| sum ^ byte);
+            data_buf.push(checksum);
+            self.handle.write_all(&data_buf)?;
+            self.get_ack()
+        } else {
+            Err(Error::Unsupported("bootloader does not support erase".to_string()))
+        }
+    }
+
+    /**
+     * 发送ExtendedErase指令擦除指定的页
+     */
+    fn extended_erase_pages(&mut self, pages: &[u16]) -> Result<(), Error> {
+        let cmd = [Command::ExtendedErase as u8, !(Command::ExtendedErase as u8)];
+        self.handle.write_all(&cmd)?;
+        self.get_ack()?;
+
+        // 页数量减一，然后是每个页号，均为高位在前，最后是所有字节的异或校验
+        let mut data_buf = Vec::with_capacity(pages.len() * 2 + 3);
+        data_buf.extend_from_slice(&((pages.len() - 1) as u16).to_be_bytes());
+        for page in pages {
+            data_buf.extend_from_slice(&page.to_be_bytes());
+        }
+        let checksum = data_buf.iter().fold(0u8, |sum, byte| sum ^ byte);
+        data_buf.push(checksum);
+
+        self.handle.write_all(&data_buf)?;
+        self.get_ack()
+    }
+
+    /**
+     * 修改选项字节后芯片会自动复位，等待复位完成后重新进入bootloader
+     */
+    fn reconnect_after_reset(&mut self) -> Result<(), Error> {
+        LOG.info(t!("reconnect_help").as_str(), Color::BrightBlue);
+        std::thread::sleep(Duration::from_millis(100));
+        self.handle.clear(serialport::ClearBuffer::All)?;
+        self.reset_bootloader()
+    }
+
+    /**
+     * 复位后以指定的波特率握手，再读取芯片ID和指令列表确认通讯稳定
+     */
+    fn try_baud(&mut self, runtime: &Runtime, baud: u32, attempts: u32) -> bool {
+        LOG.info(t!("auto_baud_try_help", "baud" => baud).as_str(), Color::White);
+        if self.handle.set_baud_rate(baud).is_err() {
+            return false;
+        }
+        for _ in 0..attempts {
+            if runtime.block_on(self.sync()) {
+                return self.read_id().is_ok() && self.get_commands().is_ok();
+            }
+        }
+        false
+    }
+
+    /**
+     * 自动波特率：先尝试上次记录的波特率，不行时从安全的波特率开始逐级提高，
+     * 遇到NACK或超时时退回上一个稳定的波特率
+     */
+    fn auto_baud(&mut self) -> Result<(), Error> {
+        let port_name = self.handle.name().unwrap_or_default();
+        let port = list_ports()?.into_iter().find(|port| port.name == port_name);
+        let key = baud_cache::key(&port_name, port.as_ref().and_then(|port| port.vid), port.as_ref().and_then(|port| port.pid));
+
+        // 没有应答的波特率不用等太久
+        self.handle.set_timeout(Duration::from_millis(200))?;
+        let result = self.select_baud(&key);
+        self.handle.set_timeout(Duration::from_millis(2000))?;
+
+        let baud = result?;
+        baud_cache::set(&key, baud);
+        LOG.info(t!("auto_baud_selected_help", "baud" => baud).as_str(), Color::Green);
+        Ok(())
+    }
+
+    /**
+     * 选择波特率，返回时芯片已经以该波特率完成握手
+     */
+    fn select_baud(&mut self, key: &str) -> Result<u32, Error> {
+        // --baud 不为0时作为上限
+        let max = match self.air_isp.get_baud() {
+            0 => u32::MAX,
+            baud => baud,
+        };
+        let attempts = self.air_isp.get_connect_attempts();
+        let runtime = Runtime::new()?;
+
+        if let Some(baud) = baud_cache::get(key).filter(|baud| *baud <= max) {
+            if self.try_baud(&runtime, baud, 2) {
+                LOG.info(t!("auto_baud_cached_help", "baud" => baud).as_str(), Color::Green);
+                return Ok(baud);
+            }
+        }
+
+        let mut best = None;
+        for (i, baud) in BAUD_CANDIDATES.iter().copied().filter(|baud| *baud <= max).enumerate() {
+            // 第一个波特率和普通连接一样多试几次，之后的波特率失败就停止
+            let attempts = if i == 0 { attempts } else { 2 };
+            if !self.try_baud(&runtime, baud, attempts) {
+                break;
+            }
+            best = Some(baud);
+        }
+        let best = best.ok_or(Error::Connect)?;
+
+        // 失败的尝试会让bootloader锁定在错误的波特率上，需要以稳定的波特率重新握手
+        if self.handle.baud_rate()? != best && !self.try_baud(&runtime, best, attempts) {
+            return Err(Error::Connect);
+        }
+        Ok(best)
+    }
+
+    /**
+     * 按照 before 参数复位芯片进入bootloader，然后发送0x7F握手，返回是否收到ACK
+     */
+    async fn sync(&mut self) -> bool {
+        if self.before.run(&mut *self.handle).is_err() {
+            return false;
+        }
+
+        // 探测波特率和串口时会反复调用，串口被拔出时不能panic
+        let data = [0x7Fu8];
+        if self.handle.write_all(&data).is_err() {
+            return false;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        self.get_ack().is_ok()
+    }
+
+    /**
+     * 检查是否有返回ACK
+     */
+    pub fn get_ack(&mut self) -> Result<(), Error> {
+        let mut buf = [0u8; 1];
+        self.handle.read_exact(&mut buf)?;
+        if buf[0] == Ack::Ack as u8 {
+            Ok(())
+        } else {
+            self.handle.clear(serialport::ClearBuffer::All)?; // 清空缓冲区
+            if buf[0] == Ack::Nack as u8 {
+                Err(Error::Nack)
+            } else {
+                Err(Error::ProtocolDesync(format!("unexpected byte {:#04x}", buf[0])))
+            }
+        }
+    }
+}
+
+impl Pp for GeneralUart<'_> {
+    fn write_flash(
+        &mut self,
+        address: u32,
+        data: &[u8],
+        progress: &Arc<dyn ProgressSink>,
+    ) -> Result<(), Error> {
+        LOG.info(t!("write_flash_file_help").as_str(), Color::BrightBlue);
+        let now_time = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis();
+
+        if !data.is_empty() {
+            offset_address(address, data.len() - 1)?;
+        }
+        progress.start(Operation::Write, address, data.len());
+        // 一次最多写255个字节
+        for i in (0..data.len()).step_by(256) {
+            let cmd = [Command::WriteMemory as u8, !(Command::WriteMemory as u8)];
+            let mut data_len = 256;
+            if i + 256 > data.len() {
+                data_len = data.len() - i;
+            }
+            data_len += 2; // 加上数据大小和校验位
+            let mut data_buf = vec![0u8; data_len];
+            let real_data_len = data_len - 3; // 真实数据长度
+            let chunk = &data[i..i + data_len - 2];
+            data_buf[0] = real_data_len as u8;
+            data_buf[1..data_len - 1].copy_from_slice(chunk);
+            data_buf[data_len - 1] = chunk.iter().fold(data_buf[0], |sum, byte| sum ^ byte);
+            // 发送指令
+            self.handle.write_all(&cmd)?;
+            self.get_ack()?;
+
+            // 发送地址
+            let mut address_buf = vec![0u8; 5];
+            let block_address = offset_address(address, i)?;
+            address_buf[0] = (block_address >> 24) as u8;
+            address_buf[1] = (block_address >> 16) as u8;
+            address_buf[2] = (block_address >> 8) as u8;
+            address_buf[3] = block_address as u8;
+            address_buf[4] = address_buf[0] ^ address_buf[1] ^ address_buf[2] ^ address_buf[3];
+            self.handle.write_all(&address_buf)?;
+            self.get_ack()?;
+
+            // 发送数据
+            self.handle.write_all(&data_buf)?;
+            self.get_ack()?;
+            // 已经发送的数据
+            progress.update(i + real_data_len + 1, data.len());
+        }
+        progress.finish();
+
+        LOG.info(t!("write_flash_success_help",
+                    "time" => format!("{}", std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)
+                        .unwrap()
+                        .as_millis() - now_time),
+                    "addr" => format!("{:#010x}", address),
+                    "size" => format!("{}", data.len())
+        ).as_str(),Color::Green);
+        LOG.print("\n");
+
+        Ok(())
+    }
+
+    fn read_flash(
+        &mut self,
+        address: u32,
+        size: u32,
+        progress: &Arc<dyn ProgressSink>,
+    ) -> Result<Vec<u8>, Error> {
+        LOG.info(t!("read_flash_file_help").as_str(), Color::BrightBlue);
+        let now_time = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis();
+
+        if size > 0 {
+            offset_address(address, size as usize - 1)?;
+        }
+        let mut data = Vec::with_capacity(size as usize);
+        progress.start(Operation::Read, address, size as usize);
+        // 一次最多读256个字节
+        for i in (0..size).step_by(256) {
+            let data_len = 256.min(size - i);
+            let data_buf = self.read_memory(offset_address(address, i as usize)?, data_len as usize)?;
+            data.extend_from_slice(&data_buf);
+
+            progress.update((i + data_len) as usize, size as usize);
+        }
+        progress.finish();
+
+        LOG.info(t!("read_flash_success_help",
+                    "time" => format!("{}", std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)
+                        .unwrap()
+                        .as_millis() - now_time),
+                    "addr" => format!("{:#010x}", address),
+                    "size" => format!("{}", size)
+        ).as_str(),Color::Green);
+
+        Ok(data)
+    }
+
+    fn get_chip_id(&mut self) -> Result<(), Error> {
+        let id = self.read_id()?;
+
+        let mut chip_id: String = Default::default();
+        for i in id.iter() {
+            chip_id.push_str(&format!("{:#04x} ", i));
+        }
+        LOG.info(t!("get_chip_success_help","chip_id" => chip_id).as_str(),Color::Blue);
+        Ok(())
+    }
+
+    fn reset_bootloader(&mut self) -> Result<(), Error> {
+        if self.air_isp.get_auto_baud() && !self.baud_selected {
+            // 选好波特率后仍然走下面的连接流程，输出连接动画和 connect_attempt 事件
+            self.auto_baud()?;
+            self.baud_selected = true;
+        }
+
+        LOG.print(&t!("connect_help"));
+
+        // 打印进度条
+        let runtime = Runtime::new()?;
+
+        // 动画在tokio的线程中打印，需要沿用当前线程的屏蔽设置
+        let muted = LOG.is_muted();
+        let connected = runtime.block_on(async {
+            let is_cancelled = Arc::new(AtomicBool::new(false));
+            let is_cancelled_for_task = Arc::clone(&is_cancelled);
+
+            let log_task = tokio::spawn(async move {
+                let mut count = 0;
+                let mut write_flag = true;
+
+                loop {
+                    if is_cancelled_for_task.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    // 任务可能在不同的线程上继续执行
+                    LOG.mute_thread(muted);
+
+                    if count >= 3 {
+                        write_flag = !write_flag;
+                        count = 0;
+                        tokio::time::sleep(Duration::from_millis(1000)).await;
+                    }
+
+                    if write_flag {
+                        LOG.print(".");
+                    } else {
+                        LOG.print("_");
+                    }
+
+                    count += 1;
+                    tokio::time::sleep(Duration::from_millis(200)).await;
+                }
+            });
+
+            let mut connected = false;
+            for i in 0..self.air_isp.get_connect_attempts() {
+                LOG.event(&Event::ConnectAttempt {
+                    port: self.handle.name().unwrap_or_default(),
+                    attempt: i + 1,
+                    max: self.air_isp.get_connect_attempts(),
+                });
+                if self.sync().await {
+                    connected = true;
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(200)).await;
+            }
+
+            // 取消任务
+            is_cancelled.store(true, Ordering::SeqCst);
+            log_task.await.unwrap();
+            connected
+        });
+        LOG.print("\n"); // 换行
+        if !connected {
+            return Err(Error::Connect);
+        }
+
+        // 读取Chip ID
+        let retry = 3;
+        for i in 0..retry {
+            match self.get_chip_id() {
+                Ok(_) => {
+                    break;
+                }
+                Err(e) => {
+                    if i == retry - 1 {
+                        return match e {
+                            Error::Timeout => Err(e),
+                            _ => Err(Error::ProtocolDesync(t!("get_chip_id_fail_help"))),
+                        };
+                    }
+                    std::thread::sleep(Duration::from_millis(100));
+                    //也许你看到这行代码的时候会感觉疑惑，这看起来是一个非常愚蠢的行为，让人无法理解。
+                    //但是事实并不是这样，经过逻辑分析仪的抓取，我们发现使用CDC驱动的USB转串口，在Windows下使能RTS或者DTR似乎会发出一个奇怪的字节，
+                    //这个字节可能是0x7F或者0xFD等，暂时还没找到什么规律。但是正因为串入了这个字节，因此mcu接收到的第一个字节就不是我们发送的用来握手的0x7F，
+                    //这样后续的整个指令将会完全乱掉，因此我们额外添加了一个字节去处理，假如GetID操作失败的话，很有可能就是因为发送的指令乱掉了，那么我们手动
+                    //加入一个字节来补全，并尝试重试3次。
+                    // 也许在RUST中我们可以去掉（逃
+                    let data = [0x7Fu8];
+                    self.handle.write_all(&data)?;
+                    std::thread::sleep(Duration::from_millis(5));
+                    // 取出串口缓冲区的数据
+                    self.handle.clear(serialport::ClearBuffer::All)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn erase_all(&mut self) -> Result<(), Error>
+    {
+        LOG.info(t!("erase_all_help").as_str(), Color::BrightBlue);
+        let now_time = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis();
+
+        match self.mass_erase() {
+            Ok(_) => {
+                let run_time = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)
+                    .unwrap()
+                    .as_millis() - now_time;
+
+                LOG.info(t!("erase_all_success_help",
+                            "time" => format!("{}", run_time)
+                ).as_str(),Color::Green);
+            }
+            Err(e) => {
+                LOG.info(t!("erase_all_fail_help").as_str(), Color::Red);
+                return Err(e);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn erase_pages(&mut self, address: u32, size: u32) -> Result<(), Error>
+    {
+        let pages = self.get_chip_info()?.pages(address, size)?;
+        LOG.info(t!("erase_pages_help",
+                    "addr" => format!("{:#010x}", address),
+                    "size" => format!("{}", size),
+                    "first" => format!("{}", pages.start),
+                    "last" => format!("{}", pages.end - 1)
+                 ).as_str(), Color::BrightBlue);
+        let now_time = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis();
+
+        // 一次擦除太多页可能会导致等待ACK超时，因此分批擦除
+        let pages = pages.map(|page| page as u16).collect::<Vec<u16>>();
+        for chunk in pages.chunks(32) {
+            if let Err(e) = self.erase_page_list(chunk) {
+                LOG.info(t!("erase_all_fail_help").as_str(), Color::Red);
+                return Err(e);
+            }
+        }
+
+        LOG.info(t!("erase_all_success_help",
+                    "time" => format!("{}", std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)
+                        .unwrap()
+                        .as_millis() - now_time)
+        ).as_str(),Color::Green);
+        Ok(())
+    }
+
+    fn read_protect(&mut self) -> Result<(), Error>
+    {
+        LOG.info(t!("read_protect_help").as_str(), Color::BrightBlue);
+
+        let cmd = [Command::ReadProtect as u8, !(Command::ReadProtect as u8)];
+        self.handle.write_all(&cmd)?;
+        self.get_ack()?;
+        // 选项字节写入完成后会再返回一次ACK
+        self.get_ack()?;
+
+        LOG.info(t!("read_protect_success_help").as_str(), Color::Green);
+        self.reconnect_after_reset()
+    }
+
+    fn read_unprotect(&mut self) -> Result<(), Error>
+    {
+        LOG.info(t!("read_unprotect_help").as_str(), Color::BrightBlue);
+        let now_time = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis();
+
+        let cmd = [Command::ReadUnprotect as u8, !(Command::ReadUnprotect as u8)];
+        self.handle.write_all(&cmd)?;
+        self.get_ack()?;
+
+        // 解除读保护会擦除全片，耗时较长，临时延长超时时间
+        self.handle.set_timeout(Duration::from_millis(30000))?;
+        let result = self.get_ack();
+        self.handle.set_timeout(Duration::from_millis(2000))?;
+        result?;
+
+        LOG.info(t!("read_unprotect_success_help",
+                    "time" => format!("{}", std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)
+                        .unwrap()
+                        .as_millis() - now_time)
+        ).as_str(), Color::Green);
+        self.reconnect_after_reset()
+    }
+
+    fn write_protect(&mut self, sectors: &[u8]) -> Result<(), Error>
+    {
+        LOG.info(t!("write_protect_help",
+            "sectors" => format!("{:?}", sectors)
+        ).as_str(), Color::BrightBlue);
+
+        let cmd = [Command::WriteProtect as u8, !(Command::WriteProtect as u8)];
+        self.handle.write_all(&cmd)?;
+        self.get_ack()?;
+
+        // 扇区数量减一，然后是每个扇区号，最后是所有字节的异或校验
+        let mut data_buf = Vec::with_capacity(sectors.len() + 2);
+        data_buf.push((sectors.len() - 1) as u8);
+        data_buf.extend_from_slice(sectors);
+        let checksum = data_buf.iter().fold(0u8, |sum, byte| sum ^ byte);
+        data_buf.push(checksum);
+        self.handle.write_all(&data_buf)?;
+        self.get_ack()?;
+
+        LOG.info(t!("write_protect_success_help").as_str(), Color::Green);
+        self.reconnect_after_reset()
+    }
+
+    fn write_unprotect(&mut self) -> Result<(), Error>
+    {
+        LOG.info(t!("write_unprotect_help").as_str(), Color::BrightBlue);
+
+        let cmd = [Command::WriteUnprotect as u8, !(Command::WriteUnprotect as u8)];
+        self.handle.write_all(&cmd)?;
+        self.get_ack()?;
+        // 选项字节写入完成后会再返回一次ACK
+        self.get_ack()?;
+
+        LOG.info(t!("write_unprotect_success_help").as_str(), Color::Green);
+        self.reconnect_after_reset()
+    }
+
+    fn go(&mut self, address: u32) -> Result<(), Error>
+    {
+        let cmd = [Command::Go as u8, !(Command::Go as u8)];
+        self.handle.write_all(&cmd)?;
+        self.get_ack()?;
+
+        let mut address_buf = address.to_be_bytes().to_vec();
+        address_buf.push(address_buf[0] ^ address_buf[1] ^ address_buf[2] ^ address_buf[3]);
+        self.handle.write_all(&address_buf)?;
+        self.get_ack()?;
+
+        LOG.info(t!("leaving_go_help", "addr" => format!("{:#010x}", address)).as_str(), Color::Green);
+        Ok(())
+    }
+
+    fn bootloader_info(&mut self) -> Result<(), Error>
+    {
+        let (version, commands) = self.get_commands()?;
+        self.capabilities = Some(commands.clone());
+        let (_, option_bytes) = self.get_version()?;
+        let info = BootloaderInfo {
+            version,
+            commands,
+            option_bytes,
+        };
+
+        LOG.info(t!("bootloader_version_help",
+            "version" => format!("{}.{}", info.version >> 4, info.version & 0x0F)
+        ).as_str(), Color::Blue);
+        LOG.info(t!("bootloader_option_bytes_help",
+            "option_bytes" => format!("{:#04x} {:#04x}", info.option_bytes[0], info.option_bytes[1])
+        ).as_str(), Color::Blue);
+        let commands = info.commands.iter()
+            .map(|code| format!("{:#04x} {}", code, Command::name(*code)))
+            .collect::<Vec<String>>()
+            .join(", ");
+        LOG.info(t!("bootloader_commands_help", "commands" => commands).as_str(), Color::Blue);
+        Ok(())
+    }
+
+    fn reset_app(&mut self) -> Result<(), Error> {
+        LOG.info(t!("leaving_help").as_str(),Color::Blue);
+        match self.after.clone() {
+            // 硬件复位
+            Some(sequence) => {
+                sequence.run(&mut *self.handle)?;
+                LOG.info(t!("leaving_hard_reset_help").as_str(),Color::Green);
+            },
+            // 通过Go指令跳转到Flash起始地址
+            None => {
+                let address = self.get_chip_info()?.flash_addr;
+                self.go(address)?;
+            },
+        }
+
+        Ok(())
+    }
+}
+
+impl chip_info for GeneralUart<'_> {
+    fn get_chip_info(&mut self) -> Result<&peripheral::ChipInfo, Error> {
+        if let Some(info) = self.info {
+            return Ok(info);
+        }
+
+        let chip_name = self.air_isp.get_chip().to_lowercase();
+        let pid = if chip_name == "auto" {
+            Some(self.get_chip_pid()? as u16)
+        } else {
+            None
+        };
+        for chip in CHIPS.iter() {
+            for i in chip.info.iter() {
+                // 指定了型号时按名称匹配，否则按PID匹配
+                let matched = match pid {
+                    Some(pid) => i.pid == pid,
+                    None => i.name.to_lowercase() == chip_name,
+                };
+                if matched {
+                    LOG.event(&Event::ChipDetected { chip: i.name.to_string(), pid: i.pid });
+                    self.info = Some(i);
+                    return Ok(i);
+                }
+            }
+        }
+
+        Err(Error::ChipMismatch(match pid {
+            Some(pid) => format!("pid {:#06x}, {}", pid, t!("get_chip_auto_fail_help")),
+            None => chip_name,
+        }))
+    }
+    fn get_flash_size(&mut self) -> Result<u32, Error> {
+        let info = self.get_chip_info()?;
+        let (flash_size_reg, flash_size) = (info.flash_size_reg, info.flash_size);
+        if flash_size_reg == 0xFFFFFFFF {
+            // 没有Flash大小寄存器，使用配置文件中的大小
+            return Ok(flash_size);
+        }
+        // Flash大小寄存器的单位是KB
+        let data = self.read_memory(flash_size_reg, 2)?;
+        Ok(u16::from_le_bytes([data[0], data[1]]) as u32 * 1024)
+    }
+    fn get_chip_pid(&mut self) -> Result<u32, Error> {
+        let id = self.read_id()?;
+        let pid = id.iter().fold(0u32, |pid, byte| (pid << 8) | *byte as u32);
+        Ok(pid)
+    }
+}
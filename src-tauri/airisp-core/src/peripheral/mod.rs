@@ -0,0 +1,96 @@
+pub mod general_uart;
+pub mod swd;
+use crate::error::Error;
+use crate::peripheral;
+use crate::progress::ProgressSink;
+use std::sync::Arc;
+
+include!(concat!(env!("OUT_DIR"), "/chips.rs"));
+
+impl ChipInfo {
+    /// 计算地址范围覆盖到的页号，返回 [起始页, 结束页)
+    pub fn pages(&self, address: u32, size: u32) -> Result<std::ops::Range<u32>, Error> {
+        let end = address as u64 + size as u64;
+        if size == 0 || address < self.flash_addr || end > self.flash_addr as u64 + self.flash_size as u64 {
+            return Err(Error::InvalidInput(format!("region {:#010x}..{:#010x} is outside flash", address, end)));
+        }
+        let first = (address - self.flash_addr) / self.page_size;
+        let last = ((end - 1) as u32 - self.flash_addr) / self.page_size;
+        Ok(first..last + 1)
+    }
+}
+
+/// 按名称查找芯片，不区分大小写
+pub fn find_chip(name: &str) -> Option<&'static ChipInfo> {
+    let name = name.to_lowercase();
+    for chip in CHIPS.iter() {
+        for i in chip.info.iter() {
+            if i.name.to_lowercase() == name {
+                return Some(i);
+            }
+        }
+    }
+    None
+}
+
+#[allow(non_camel_case_types)]
+pub trait chip_info {
+    fn get_chip_info(&mut self) -> Result<&ChipInfo, Error>;
+    /// 读取芯片实际的Flash大小，没有Flash大小寄存器时使用配置文件中的大小
+    fn get_flash_size(&mut self) -> Result<u32, Error>;
+    fn get_chip_pid(&mut self) -> Result<u32, Error>;
+}
+
+pub trait Pp: chip_info {
+    fn write_flash(&mut self ,address: u32, data: &[u8], progress: &Arc<dyn ProgressSink>) -> Result<(), Error>;
+
+    /// 读取Flash
+    fn read_flash(&mut self, address: u32, size: u32, progress: &Arc<dyn ProgressSink>) -> Result<Vec<u8>, Error>;
+
+    /// 重启到bootloader
+    fn reset_bootloader(&mut self) -> Result<(), Error>;
+
+    /// 重启到APP
+    fn reset_app(&mut self) -> Result<(), Error>;
+
+    /// 不复位，直接跳转到指定地址运行
+    fn go(&mut self, address: u32) -> Result<(), Error>;
+
+    /// 获取芯片ID
+    fn get_chip_id(&mut self) -> Result<(), Error>;
+
+    /// 获取bootloader版本、选项字节和支持的指令
+    fn bootloader_info(&mut self) -> Result<(), Error>;
+
+    /// 擦除全片
+    fn erase_all(&mut self) -> Result<(), Error>;
+
+    /// 擦除地址范围覆盖到的所有页
+    fn erase_pages(&mut self, address: u32, size: u32) -> Result<(), Error>;
+
+    /// 开启读保护，芯片会自动复位
+    fn read_protect(&mut self) -> Result<(), Error>;
+
+    /// 解除读保护，芯片会擦除全片并自动复位
+    fn read_unprotect(&mut self) -> Result<(), Error>;
+
+    /// 对指定扇区开启写保护，芯片会自动复位
+    fn write_protect(&mut self, sectors: &[u8]) -> Result<(), Error>;
+
+    /// 解除所有扇区的写保护，芯片会自动复位
+    fn write_unprotect(&mut self) -> Result<(), Error>;
+}
+
+pub enum Peripheral<'a> {
+    Swd(peripheral::swd::Swd<'a>),
+    GeneralUart(peripheral::general_uart::GeneralUart<'a>),
+}
+
+impl<'a> Peripheral<'a> {
+    pub fn get_pp(&mut self) -> &mut dyn Pp {
+        match self {
+            Peripheral::Swd(pp) => pp,
+            Peripheral::GeneralUart(pp) => pp,
+        }
+    }
+}
\ No newline at end of file
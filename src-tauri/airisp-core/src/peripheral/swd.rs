@@ -0,0 +1,368 @@
+use crate::error::Error;
+use std::cell::Cell;
+use std::sync::Arc;
+
+use colored::Color;
+use probe_rs::flashing::{DownloadOptions, FlashProgress, ProgressEvent};
+use probe_rs::probe::list::Lister;
+use probe_rs::{flashing, MemoryInterface, Permissions, Session};
+use rust_i18n::t;
+use serde::Serialize;
+
+use crate::event::Event;
+use crate::log::LOG;
+use crate::progress::{Operation, ProgressSink};
+use crate::peripheral;
+use crate::peripheral::Pp;
+use crate::config;
+
+use crate::peripheral::{chip_info, ChipInfo, CHIPS};
+
+/// 系统中的调试器
+#[derive(Serialize, Clone)]
+pub struct ProbeInfo {
+    pub identifier: String,
+    pub probe_type: String,
+    pub vid: u16,
+    pub pid: u16,
+    pub serial_number: Option<String>,
+}
+
+/// 列出系统中所有的调试器
+pub fn list_probes() -> Vec<ProbeInfo> {
+    Lister::new()
+        .list_all()
+        .into_iter()
+        .map(|probe| ProbeInfo {
+            identifier: probe.identifier.clone(),
+            probe_type: format!("{:?}", probe.probe_type),
+            vid: probe.vendor_id,
+            pid: probe.product_id,
+            serial_number: probe.serial_number.clone(),
+        })
+        .collect()
+}
+
+pub struct Swd<'a> {
+    air_isp: &'a config::Config,
+    info: ChipInfo,
+}
+
+impl Swd<'_> {
+    pub fn new(air_isp: &config::Config) -> Result<Swd<'_>, Error> {
+        let mut swd =
+        Swd {
+            air_isp,
+            info: ChipInfo { // 用于初始化，在后面会被修改
+                name: "",
+                debug_idcode_reg: 0,
+                pid: 0,
+                flash_size_reg: 0,
+                flash_addr: 0,
+                flash_size: 0,
+                page_size: 0,
+                sector_size: 0,
+                ram_size: 0,
+                uid_reg: 0,
+                uf2_family_id: 0,
+            },
+        };
+        LOG.event(&Event::ConnectAttempt {
+            port: air_isp.get_port(),
+            attempt: 1,
+            max: 1,
+        });
+        swd.info = swd.get_chip_info()?.clone();
+        LOG.event(&Event::ChipDetected { chip: swd.info.name.to_string(), pid: swd.info.pid });
+        Ok(swd)
+    }
+
+    fn get_chip_session_name(&mut self, chip_name: &str) -> Result<Session, Error> {
+        let session;
+        let chip_name = chip_name.to_lowercase();
+        let mut speed = self.air_isp.get_baud();
+        if speed == 0 {
+            speed = 200; // 默认速度200k
+        }
+
+        // session = Session::auto_attach(chip_name, Permissions::default())?;
+        if self.air_isp.get_port() == "auto" {
+            session = Session::auto_attach(chip_name, Permissions::default())?;
+            return Ok(session);
+        } else {
+            let lister = Lister::new();
+            let probe_list = lister.list_all();
+            for i in probe_list {
+                // 输入的端口名称和扫描到的名称子串匹配，或者和调试器序列号相同
+                let port = self.air_isp.get_port().to_lowercase();
+                if i.identifier.to_lowercase().contains(port.as_str())
+                    || i.serial_number.as_deref().is_some_and(|serial| serial.to_lowercase() == port)
+                {
+                    let mut probe = i.open(&lister)?;
+                    probe.set_speed(speed)?;
+                    session = probe.attach(chip_name, Permissions::default())?;
+                    return Ok(session);
+                }
+            }
+        }
+        Err(Error::Probe("get probe fail".to_string()))
+    }
+
+    fn get_chip_session(&mut self) -> Result<Session, Error> {
+        let chip_name = self.info.name;
+        self.get_chip_session_name(chip_name)
+    }
+}
+
+impl chip_info for Swd<'_> {
+    fn get_chip_info(&mut self) -> Result<&peripheral::ChipInfo, Error> {
+        // 自动判断芯片型号
+        if self.air_isp.get_chip() == "auto" {
+            for chip in CHIPS.iter() {
+                for i in chip.info.iter() {
+                    // 0xFFFFFFFF 证明暂且未知，因此假设就是这个芯片，不进行进一步的判断
+                    return if i.debug_idcode_reg == 0xFFFFFFFF {
+                        Ok(i)
+                    } else {
+                        let mut session =
+                            Session::auto_attach("cortex-m0", Permissions::default())?; // 默认使用m0去连接，一般可以连上
+                        let mut core = session.core(0)?;
+                        let pid = core.read_word_32(i.debug_idcode_reg as u64)?;
+                        if pid != i.pid as u32 {
+                            continue;
+                        }
+                        Ok(i)
+                    };
+                }
+            }
+
+        }
+        //if self.air_isp.get_chip() == "auto"
+        else
+        // 有具体的型号
+        {
+            let chip_name = self.air_isp.get_chip();
+            for chip in CHIPS.iter() {
+                for i in chip.info.iter() {
+                    // 全部转换成小写，然后进行匹配
+                    if i.name.to_lowercase() == chip_name.to_lowercase() {
+                        if i.debug_idcode_reg == 0xFFFFFFFF {
+                            LOG.warn(
+                                t!("swd_pid_not_match_unknown_no_pid_help").as_str(),
+                            );
+                            return Ok(i);
+                        }
+
+                        let mut session = self.get_chip_session_name(chip_name.as_str())?;
+                        let mut core = session.core(0)?;
+                        let pid = match core.read_word_32(i.debug_idcode_reg as u64) {
+                            Ok(pid) => pid,
+                            Err(_) => {
+                                LOG.warn(
+                                    t!("swd_read_debug_idcode_fail_help",
+                                        "addr" => format!("{:#010x}", i.debug_idcode_reg)
+                                    )
+                                    .as_str(),
+                                );
+                                continue;
+                            }
+                        };
+                        if pid != i.pid as u32 {
+                            LOG.warn(t!("swd_pid_not_match_help",
+                                "set_pid" => format!("{:#04x} {:#04x}", (i.pid >> 8) & 0xFF, i.pid & 0xFF),
+                                "read_pid" => format!("{:#04x} {:#04x}", (pid >> 8) & 0xFF, pid & 0xFF),
+                            ).as_str());
+                        }
+                        return Ok(i);
+                    }
+                }
+            }
+        };
+
+        // 都没有找到，返回错误
+        Err(Error::ChipMismatch(t!("swd_pid_not_match_unknown_help")))
+    }
+    fn get_flash_size(&mut self) -> Result<u32, Error> {
+        if self.info.flash_size_reg == 0xFFFFFFFF {
+            // 没有Flash大小寄存器，使用配置文件中的大小
+            return Ok(self.info.flash_size);
+        }
+        // Flash大小寄存器的单位是KB
+        let mut session = self.get_chip_session()?;
+        let mut core = session.core(0)?;
+        let size = core.read_word_16(self.info.flash_size_reg as u64)?;
+        Ok(size as u32 * 1024)
+    }
+    fn get_chip_pid(&mut self) -> Result<u32, Error> {
+        let print_pid = |pid: u16| {
+            LOG.info(
+                t!("get_chip_success_help",
+                    "chip_id" => format!("{:#04x} {:#04x}", (pid >> 8) & 0xFF, pid & 0xFF),
+                )
+                .as_str(),
+                Color::BrightBlue,
+            );
+        };
+        let pid = self.get_chip_info()?.pid;
+        print_pid(pid);
+        Ok(pid as u32)
+    }
+}
+
+impl Pp for Swd<'_> {
+    fn write_flash(
+        &mut self,
+        address: u32,
+        data: &[u8],
+        progress: &Arc<dyn ProgressSink>,
+    ) -> Result<(), Error> {
+        let mut session = self.get_chip_session()?;
+        let mut loader = session.target().flash_loader();
+
+        LOG.info(t!("write_flash_file_help").as_str(), Color::BrightBlue);
+        let now_time = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis();
+        loader.add_data(address as u64, data)?;
+
+        // 通过flash loader的回调统计已经写入的字节数，按页写入时可能超过数据长度
+        progress.start(Operation::Write, address, data.len());
+        let sink = progress.clone();
+        let total = data.len();
+        let done = Cell::new(0usize);
+        let flash_progress = FlashProgress::new(move |event| {
+            if let ProgressEvent::PageProgrammed { size, .. } = event {
+                done.set((done.get() + size as usize).min(total));
+                sink.update(done.get(), total);
+            }
+        });
+        let mut options = DownloadOptions::default();
+        options.progress = Some(flash_progress);
+        loader.commit(&mut session, options)?;
+        progress.finish();
+
+        LOG.info(t!("write_flash_success_help",
+                    "time" => format!("{}", std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)
+                        .unwrap()
+                        .as_millis() - now_time),
+                    "addr" => format!("{:#010x}", address),
+                    "size" => format!("{}", data.len())
+                ).as_str(), Color::Green);
+        Ok(())
+    }
+    fn read_flash(
+        &mut self,
+        address: u32,
+        size: u32,
+        progress: &Arc<dyn ProgressSink>,
+    ) -> Result<Vec<u8>, Error> {
+        let mut session = self.get_chip_session()?;
+        let mut core = session.core(0)?;
+
+        LOG.info(t!("read_flash_file_help").as_str(), Color::BrightBlue);
+        let now_time = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis();
+
+        let mut data = vec![0u8; size as usize];
+        progress.start(Operation::Read, address, size as usize);
+        // 分块读取，方便显示进度
+        for i in (0..size as usize).step_by(1024) {
+            let end = (i + 1024).min(size as usize);
+            core.read(address as u64 + i as u64, &mut data[i..end])?;
+            progress.update(end, size as usize);
+        }
+        progress.finish();
+
+        LOG.info(t!("read_flash_success_help",
+                    "time" => format!("{}", std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)
+                        .unwrap()
+                        .as_millis() - now_time),
+                    "addr" => format!("{:#010x}", address),
+                    "size" => format!("{}", size)
+                ).as_str(), Color::Green);
+        Ok(data)
+    }
+    fn reset_bootloader(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+    fn get_chip_id(&mut self) -> Result<(), Error> {
+        self.get_chip_pid()?;
+        Ok(())
+    }
+
+    fn erase_all(&mut self) -> Result<(), Error> {
+        LOG.info(t!("erase_all_help").as_str(), Color::BrightBlue);
+        let now_time = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis();
+        let mut session = self.get_chip_session()?;
+        flashing::erase_all(&mut session, None)?;
+
+        LOG.info(t!("erase_all_success_help",
+                    "time" => format!("{}", std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)
+                        .unwrap()
+                        .as_millis() - now_time)
+                ).as_str(), Color::Green);
+        Ok(())
+    }
+    fn erase_pages(&mut self, address: u32, size: u32) -> Result<(), Error> {
+        let pages = self.info.pages(address, size)?;
+        LOG.info(t!("erase_pages_help",
+            "addr" => format!("{:#010x}", address),
+            "size" => format!("{}", size),
+            "first" => format!("{}", pages.start),
+            "last" => format!("{}", pages.end - 1)
+        ).as_str(), Color::BrightBlue);
+        let now_time = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis();
+        let mut session = self.get_chip_session()?;
+        flashing::erase_sectors(&mut session, None, pages.start as usize, pages.len())?;
+
+        LOG.info(t!("erase_all_success_help",
+                    "time" => format!("{}", std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)
+                        .unwrap()
+                        .as_millis() - now_time)
+                ).as_str(), Color::Green);
+        Ok(())
+    }
+    fn read_protect(&mut self) -> Result<(), Error> {
+        Err(Error::Unsupported("read protect is not supported by swd".to_string()))
+    }
+    fn read_unprotect(&mut self) -> Result<(), Error> {
+        Err(Error::Unsupported("read unprotect is not supported by swd".to_string()))
+    }
+    fn write_protect(&mut self, _sectors: &[u8]) -> Result<(), Error> {
+        Err(Error::Unsupported("write protect is not supported by swd".to_string()))
+    }
+    fn write_unprotect(&mut self) -> Result<(), Error> {
+        Err(Error::Unsupported("write unprotect is not supported by swd".to_string()))
+    }
+    fn go(&mut self, address: u32) -> Result<(), Error> {
+        let mut session = self.get_chip_session()?;
+        let mut core = session.core(0)?;
+        core.halt(std::time::Duration::from_millis(100))?;
+        // 与bootloader的Go指令一致，从向量表中取出栈指针和复位地址
+        let sp = core.read_word_32(address as u64)?;
+        let pc = core.read_word_32(address as u64 + 4)?;
+        core.write_core_reg(core.stack_pointer(), sp)?;
+        core.write_core_reg(core.program_counter(), pc)?;
+        core.run()?;
+        LOG.info(t!("leaving_go_help", "addr" => format!("{:#010x}", address)).as_str(), Color::Green);
+        Ok(())
+    }
+    fn bootloader_info(&mut self) -> Result<(), Error> {
+        Err(Error::Unsupported("bootloader info is not supported by swd".to_string()))
+    }
+    fn reset_app(&mut self) -> Result<(), Error> {
+        LOG.info(t!("leaving_help").as_str(), Color::Blue);
+        let mut session = self.get_chip_session()?;
+        session.core(0)?.reset()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,122 @@
+use std::sync::{Arc, Mutex};
+use colored::Colorize;
+use rust_i18n::t;
+use crate::event::Event;
+use crate::log::LOG;
+
+/// 正在进行的操作，用于显示不同的提示文字
+#[derive(Clone, Copy, PartialEq)]
+pub enum Operation {
+    Read,
+    Write,
+}
+
+/// 读写进度的接收者，外设在读写过程中调用
+pub trait ProgressSink: Send + Sync {
+    /// 开始读写 address 开始的 total 个字节
+    fn start(&self, _operation: Operation, _address: u32, _total: usize) {}
+    /// 已经完成 done 个字节，共 total 个字节
+    fn update(&self, done: usize, total: usize);
+    /// 读写结束
+    fn finish(&self) {}
+}
+
+/// 内置的进度显示方式
+#[derive(PartialEq, Clone, Copy)]
+pub enum Progress {
+    None,
+    Bar,
+    Percent,
+    Json,
+}
+
+impl Progress {
+    pub fn sink(self) -> Arc<dyn ProgressSink> {
+        match self {
+            Progress::None => Arc::new(Silent),
+            Progress::Bar => Arc::new(Bar::default()),
+            Progress::Percent => Arc::new(Percent::default()),
+            Progress::Json => Arc::new(Json),
+        }
+    }
+}
+
+/// 不显示进度
+pub struct Silent;
+
+impl ProgressSink for Silent {
+    fn update(&self, _done: usize, _total: usize) {}
+}
+
+/// 在同一行显示百分比和当前地址
+pub struct Percent {
+    state: Mutex<(Operation, u32)>,
+}
+
+impl Default for Percent {
+    fn default() -> Self {
+        Percent {
+            state: Mutex::new((Operation::Write, 0)),
+        }
+    }
+}
+
+impl ProgressSink for Percent {
+    fn start(&self, operation: Operation, address: u32, _total: usize) {
+        *self.state.lock().unwrap() = (operation, address);
+    }
+
+    fn update(&self, done: usize, total: usize) {
+        let (operation, address) = *self.state.lock().unwrap();
+        let percent = format!("{:.2}", done as f32 / total.max(1) as f32 * 100.0);
+        let addr = format!("{:#010x}", address as usize + done);
+        let msg = match operation {
+            Operation::Write => t!("write_flash_file_percent", "percent" => percent, "addr" => addr),
+            Operation::Read => t!("read_flash_file_percent", "percent" => percent, "addr" => addr),
+        };
+        // 清除当前行
+        LOG.print(&format!("\r{}", msg.bright_blue()));
+    }
+
+    fn finish(&self) {
+        LOG.print("\n");
+    }
+}
+
+/// 终端进度条
+pub struct Bar {
+    width: usize,
+}
+
+impl Default for Bar {
+    fn default() -> Self {
+        Bar { width: 40 }
+    }
+}
+
+impl ProgressSink for Bar {
+    fn update(&self, done: usize, total: usize) {
+        let total = total.max(1);
+        let filled = (done * self.width / total).min(self.width);
+        LOG.print(&format!("\r[{}{}] {:>3}% {}/{}",
+            "#".repeat(filled).bright_blue(),
+            "-".repeat(self.width - filled),
+            done * 100 / total,
+            done,
+            total
+        ));
+    }
+
+    fn finish(&self) {
+        LOG.print("\n");
+    }
+}
+
+/// 每次更新输出一行 progress 事件
+pub struct Json;
+
+impl ProgressSink for Json {
+    fn update(&self, done: usize, total: usize) {
+        LOG.event(&Event::Progress { done, total });
+    }
+}
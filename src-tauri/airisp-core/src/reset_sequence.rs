@@ -0,0 +1,167 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+use rust_i18n::t;
+use serialport::SerialPort;
+use crate::error::Error;
+
+/// 使用异或电路进入bootloader
+pub const DEFAULT_RESET: &str = "rts=0,dtr=0,sleep=50,dtr=1,rts=0,sleep=20,rts=1,dtr=0,sleep=5,rts=0,dtr=1,sleep=5,dtr=0";
+/// 使用直连电路进入bootloader
+pub const DIRECT_CONNECT: &str = "dtr=1,rts=0,sleep=20,rts=1,dtr=0,rts=0,dtr=1,sleep=5,dtr=0";
+/// 使用异或电路复位运行APP
+pub const HARD_RESET: &str = "rts=1,dtr=0,sleep=20,rts=0,dtr=0";
+/// 使用直连电路复位运行APP
+pub const DIRECT_CONNECT_HARD_RESET: &str = "rts=1,dtr=1,sleep=20,rts=0,dtr=1";
+
+/// 单步延时的上限（毫秒）
+pub const MAX_SLEEP_MS: u64 = 5000;
+/// 整个序列延时总和的上限（毫秒），避免每次连接重试都等待太久
+pub const MAX_TOTAL_MS: u64 = 10000;
+
+/// 复位序列中的一步
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Step {
+    Dtr(bool),
+    Rts(bool),
+    /// 延时，单位毫秒
+    Sleep(u64),
+}
+
+/// 控制DTR和RTS的复位序列，例如 "dtr=1,rts=0,sleep=20,rts=1"
+#[derive(Clone, PartialEq, Debug)]
+pub struct ResetSequence {
+    pub steps: Vec<Step>,
+}
+
+impl ResetSequence {
+    /// 解析并检查复位序列
+    pub fn parse(text: &str) -> Result<ResetSequence, Error> {
+        let invalid = |msg: String| Error::InvalidInput(t!("reset_sequence_invalid_help", "sequence" => text, "msg" => msg));
+
+        let mut steps = Vec::new();
+        for item in text.split(',') {
+            let item = item.trim();
+            let (key, value) = item
+                .split_once('=')
+                .ok_or_else(|| invalid(t!("reset_sequence_step_help", "step" => item)))?;
+            let level = || match value.trim() {
+                "0" => Ok(false),
+                "1" => Ok(true),
+                _ => Err(invalid(t!("reset_sequence_level_help", "step" => item))),
+            };
+            let step = match key.trim().to_lowercase().as_str() {
+                "dtr" => Step::Dtr(level()?),
+                "rts" => Step::Rts(level()?),
+                "sleep" => {
+                    let ms = value
+                        .trim()
+                        .parse::<u64>()
+                        .map_err(|_| invalid(t!("reset_sequence_number_help", "step" => item)))?;
+                    if ms == 0 || ms > MAX_SLEEP_MS {
+                        return Err(invalid(t!("reset_sequence_sleep_help", "step" => item, "max" => MAX_SLEEP_MS)));
+                    }
+                    Step::Sleep(ms)
+                }
+                _ => return Err(invalid(t!("reset_sequence_step_help", "step" => item))),
+            };
+            steps.push(step);
+        }
+
+        if !steps.iter().any(|step| matches!(step, Step::Dtr(_) | Step::Rts(_))) {
+            return Err(invalid(t!("reset_sequence_no_pin_help")));
+        }
+        let total = steps
+            .iter()
+            .map(|step| match step {
+                Step::Sleep(ms) => *ms,
+                _ => 0,
+            })
+            .sum::<u64>();
+        if total > MAX_TOTAL_MS {
+            return Err(invalid(t!("reset_sequence_total_help", "max" => MAX_TOTAL_MS)));
+        }
+        Ok(ResetSequence { steps })
+    }
+
+    /// 解析 --before/--after 参数：内置的名称、配置文件中命名的序列或者直接写出的序列
+    pub fn resolve(text: &str, named: &BTreeMap<String, String>) -> Result<ResetSequence, Error> {
+        match text {
+            "default_reset" => ResetSequence::parse(DEFAULT_RESET),
+            "direct_connect" => ResetSequence::parse(DIRECT_CONNECT),
+            _ => match named.get(text) {
+                Some(sequence) => ResetSequence::parse(sequence),
+                None => ResetSequence::parse(text),
+            },
+        }
+    }
+
+    /// 在串口上执行复位序列
+    pub fn run(&self, port: &mut dyn SerialPort) -> Result<(), Error> {
+        for step in self.steps.iter() {
+            match *step {
+                // write_request_to_send是RTS，write_data_terminal_ready是DTR
+                Step::Dtr(level) => port.write_data_terminal_ready(level)?,
+                Step::Rts(level) => port.write_request_to_send(level)?,
+                Step::Sleep(ms) => std::thread::sleep(Duration::from_millis(ms)),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_ok() {
+        let cases: &[(&str, &[Step])] = &[
+            ("dtr=1", &[Step::Dtr(true)]),
+            ("rts=0,sleep=20,rts=1", &[Step::Rts(false), Step::Sleep(20), Step::Rts(true)]),
+            (" DTR = 1 , Sleep = 5 ", &[Step::Dtr(true), Step::Sleep(5)]),
+            ("rts=1,sleep=1", &[Step::Rts(true), Step::Sleep(1)]),
+            ("rts=1,sleep=5000,sleep=5000", &[Step::Rts(true), Step::Sleep(5000), Step::Sleep(5000)]),
+        ];
+        for (text, steps) in cases {
+            assert_eq!(ResetSequence::parse(text).unwrap().steps, *steps, "{}", text);
+        }
+    }
+
+    #[test]
+    fn parse_builtin() {
+        for text in [DEFAULT_RESET, DIRECT_CONNECT, HARD_RESET, DIRECT_CONNECT_HARD_RESET] {
+            assert!(ResetSequence::parse(text).is_ok(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn parse_err() {
+        let cases: &[(&str, String)] = &[
+            ("", t!("reset_sequence_step_help", "step" => "")),
+            ("dtr", t!("reset_sequence_step_help", "step" => "dtr")),
+            ("cts=1", t!("reset_sequence_step_help", "step" => "cts=1")),
+            ("dtr=2", t!("reset_sequence_level_help", "step" => "dtr=2")),
+            ("rts=1,sleep=abc", t!("reset_sequence_number_help", "step" => "sleep=abc")),
+            ("rts=1,sleep=-5", t!("reset_sequence_number_help", "step" => "sleep=-5")),
+            ("rts=1,sleep=0", t!("reset_sequence_sleep_help", "step" => "sleep=0", "max" => MAX_SLEEP_MS)),
+            ("rts=1,sleep=5001", t!("reset_sequence_sleep_help", "step" => "sleep=5001", "max" => MAX_SLEEP_MS)),
+            ("sleep=20", t!("reset_sequence_no_pin_help")),
+            ("rts=1,sleep=5000,sleep=5000,sleep=1", t!("reset_sequence_total_help", "max" => MAX_TOTAL_MS)),
+        ];
+        for (text, msg) in cases {
+            let e = ResetSequence::parse(text).unwrap_err().to_string();
+            assert!(e.contains(msg.as_str()), "{}: {}", text, e);
+        }
+    }
+
+    #[test]
+    fn resolve() {
+        let mut named = BTreeMap::new();
+        named.insert("inverted".to_string(), "dtr=0,rts=1".to_string());
+        assert_eq!(ResetSequence::resolve("default_reset", &named).unwrap(), ResetSequence::parse(DEFAULT_RESET).unwrap());
+        assert_eq!(ResetSequence::resolve("direct_connect", &named).unwrap(), ResetSequence::parse(DIRECT_CONNECT).unwrap());
+        assert_eq!(ResetSequence::resolve("inverted", &named).unwrap().steps, vec![Step::Dtr(false), Step::Rts(true)]);
+        assert_eq!(ResetSequence::resolve("rts=1", &named).unwrap().steps, vec![Step::Rts(true)]);
+        assert!(ResetSequence::resolve("hard_rest", &named).is_err());
+    }
+}
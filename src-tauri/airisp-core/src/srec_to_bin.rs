@@ -0,0 +1,189 @@
+use crate::error::Error;
+use crate::hex_to_bin::{self, Bin, Firmware};
+
+fn format_error(line: usize, msg: &str) -> Error {
+    Error::FileFormat(format!("line {}: {}", line, msg))
+}
+
+/// 校验和为长度、地址和数据所有字节之和的反码的低8位
+fn check(record: &[u8]) -> bool {
+    let mut sum: u8 = 0;
+    for byte in &record[..record.len() - 1] {
+        sum = sum.wrapping_add(*byte);
+    }
+    !sum == record[record.len() - 1]
+}
+
+pub fn srec_to_bin(srec: &str) -> Result<Firmware, Error> {
+    let mut vec_bin: Vec<Bin> = Vec::new();
+    let mut start_address = None;
+    let mut data_records: u32 = 0;
+
+    let mut bin = Bin {
+        address: 0,
+        data: Vec::new(),
+    };
+
+    for (index, line) in srec.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if line.len() < 2 || !line.starts_with('S') {
+            return Err(format_error(line_number, "record does not start with 'S'"));
+        }
+        let record_type = line.as_bytes()[1];
+        // 记录类型不是ASCII字符时，第2个字节不在字符边界上，不能直接切片
+        let digits = line.get(2..).ok_or_else(|| format_error(line_number, "invalid record type"))?;
+        let record = hex::decode(digits)
+            .map_err(|e| format_error(line_number, &format!("invalid hex digits: {}", e)))?;
+        if record.is_empty() || record.len() != record[0] as usize + 1 {
+            return Err(format_error(line_number, "record length does not match byte count"));
+        }
+        if !check(&record) {
+            return Err(format_error(line_number, "checksum error"));
+        }
+
+        // 地址字段的长度由记录类型决定
+        let address_length = match record_type {
+            b'0' | b'1' | b'5' | b'9' => 2,
+            b'2' | b'6' | b'8' => 3,
+            b'3' | b'7' => 4,
+            _ => {
+                return Err(format_error(line_number, &format!("unknown record type S{}", record_type as char)));
+            }
+        };
+        // 长度字节 + 地址 + 校验和
+        if record.len() < address_length + 2 {
+            return Err(format_error(line_number, "record is too short"));
+        }
+        let address = record[1..1 + address_length]
+            .iter()
+            .fold(0u32, |address, byte| (address << 8) | *byte as u32);
+        let data = &record[1 + address_length..record.len() - 1];
+
+        match record_type {
+            b'0' => { // 头记录
+                // 忽略不处理
+            }
+            b'1' | b'2' | b'3' => { // 数据记录
+                // 地址不连续时开始新的一段
+                if bin.address as u64 + bin.data.len() as u64 != address as u64 {
+                    if !bin.data.is_empty() {
+                        vec_bin.push(bin.clone());
+                    }
+                    bin.address = address;
+                    bin.data.clear();
+                }
+                bin.data.extend_from_slice(data);
+                data_records += 1;
+            }
+            b'5' | b'6' => { // 数据记录数量
+                if address != data_records {
+                    return Err(format_error(line_number, &format!(
+                        "record count {} does not match {} data records", address, data_records)));
+                }
+            }
+            _ => { // S7/S8/S9 起始地址记录
+                start_address = Some(address);
+            }
+        }
+    }
+    vec_bin.push(bin);
+
+    let vec_bin = hex_to_bin::merge(vec_bin);
+    if vec_bin.is_empty() {
+        return Err(format_error(srec.lines().count(), "no data record"));
+    }
+
+    Ok(Firmware {
+        bins: vec_bin,
+        start_address,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hex_to_bin::tests::{segments, Segments};
+
+    /// 生成一行记录，地址字段的长度由记录类型决定
+    fn srec(record_type: char, address: u32, data: &[u8]) -> String {
+        let address_length = match record_type {
+            '2' | '6' | '8' => 3,
+            '3' | '7' => 4,
+            _ => 2,
+        };
+        let mut record = vec![(address_length + data.len() + 1) as u8];
+        record.extend_from_slice(&address.to_be_bytes()[4 - address_length..]);
+        record.extend_from_slice(data);
+        let sum = record.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte));
+        record.push(!sum);
+        format!("S{}{}\n", record_type, hex::encode_upper(record))
+    }
+
+    #[test]
+    fn srec_to_bin_ok() {
+        // (文件内容, 各段的地址和数据, 起始地址)
+        let cases: Vec<(String, Segments, Option<u32>)> = vec![
+            // S1 + S9，头记录被忽略
+            (
+                srec('0', 0, b"HDR") + &srec('1', 0x1000, &[1, 2]) + &srec('1', 0x1002, &[3]) + &srec('9', 0x1000, &[]),
+                vec![(0x1000, vec![1, 2, 3])],
+                Some(0x1000),
+            ),
+            // S2 + S8
+            (
+                srec('2', 0x01_0000, &[4]) + &srec('2', 0x02_0000, &[5]) + &srec('8', 0x01_0000, &[]),
+                vec![(0x01_0000, vec![4]), (0x02_0000, vec![5])],
+                Some(0x01_0000),
+            ),
+            // S3 + S5 + S7
+            (
+                srec('3', 0x0800_0000, &[6, 7]) + &srec('5', 1, &[]) + &srec('7', 0x0800_0101, &[]),
+                vec![(0x0800_0000, vec![6, 7])],
+                Some(0x0800_0101),
+            ),
+            // S6 记录数量，没有起始地址
+            (srec('3', 0x0800_0000, &[8]) + &srec('3', 0x0800_0001, &[9]) + &srec('6', 2, &[]), vec![(0x0800_0000, vec![8, 9])], None),
+            // 小写的十六进制和空行
+            ("S104000001fa\n\nS9030000fc\n".to_string(), vec![(0x0000, vec![0x01])], Some(0)),
+        ];
+        for (text, expected, start_address) in cases.iter() {
+            let firmware = srec_to_bin(text).unwrap();
+            assert_eq!(segments(&firmware), *expected, "{}", text);
+            assert_eq!(firmware.start_address, *start_address, "{}", text);
+        }
+    }
+
+    #[test]
+    fn srec_to_bin_err() {
+        let cases: &[String] = &[
+            // 没有数据记录
+            String::new(),
+            srec('0', 0, b"HDR") + &srec('9', 0, &[]),
+            // 校验错误
+            "S104000001FB\n".to_string(),
+            "S2050100000AF0\n".to_string(),
+            "S30608000000FFF0\n".to_string(),
+            // 不是S开头
+            "X1040000017A\n".to_string(),
+            "S\n".to_string(),
+            // 非法字符
+            "S10400000ZFA\n".to_string(),
+            "Sé0000\n".to_string(),
+            // 长度和数据不一致
+            "S105000001FA\n".to_string(),
+            // 未知的记录类型
+            srec('4', 0, &[1]),
+            // 记录太短
+            "S301FE\n".to_string(),
+            // 记录数量不一致
+            srec('1', 0, &[1]) + &srec('5', 2, &[]),
+        ];
+        for text in cases {
+            assert!(srec_to_bin(text).is_err(), "{}", text);
+        }
+    }
+}
@@ -0,0 +1,243 @@
+use crate::error::Error;
+use crate::hex_to_bin::{self, Bin, Firmware};
+
+const BLOCK_SIZE: usize = 512;
+const MAGIC_START0: u32 = 0x0A32_4655;
+const MAGIC_START1: u32 = 0x9E5D_5157;
+const MAGIC_END: u32 = 0x0AB1_6F30;
+
+const FLAG_NOT_MAIN_FLASH: u32 = 0x0000_0001;
+const FLAG_FILE_CONTAINER: u32 = 0x0000_1000;
+const FLAG_FAMILY_ID_PRESENT: u32 = 0x0000_2000;
+
+/// 每个块中写入的数据大小
+const PAYLOAD_SIZE: usize = 256;
+
+fn format_error(msg: &str) -> Error {
+    Error::FileFormat(format!("uf2: {}", msg))
+}
+
+fn read_u32(block: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([block[offset], block[offset + 1], block[offset + 2], block[offset + 3]])
+}
+
+pub fn is_uf2(data: &[u8]) -> bool {
+    data.len() >= BLOCK_SIZE && read_u32(data, 0) == MAGIC_START0 && read_u32(data, 4) == MAGIC_START1
+}
+
+/// 解析UF2文件，family_id不为None时只保留该系列的块
+pub fn uf2_to_bin(data: &[u8], family_id: Option<u32>) -> Result<Firmware, Error> {
+    if !data.len().is_multiple_of(BLOCK_SIZE) {
+        return Err(format_error("file size is not a multiple of 512"));
+    }
+
+    let mut vec_bin = Vec::new();
+    let mut families = Vec::new();
+    for (index, block) in data.chunks(BLOCK_SIZE).enumerate() {
+        if read_u32(block, 0) != MAGIC_START0
+            || read_u32(block, 4) != MAGIC_START1
+            || read_u32(block, 508) != MAGIC_END
+        {
+            return Err(format_error(&format!("block {}: bad magic number", index)));
+        }
+        let flags = read_u32(block, 8);
+        let target_address = read_u32(block, 12);
+        let payload_size = read_u32(block, 16) as usize;
+        if payload_size > 476 {
+            return Err(format_error(&format!("block {}: payload size {} is too large", index, payload_size)));
+        }
+        // 不是写入主Flash的块或者是文件容器，直接跳过
+        if flags & (FLAG_NOT_MAIN_FLASH | FLAG_FILE_CONTAINER) != 0 {
+            continue;
+        }
+
+        if flags & FLAG_FAMILY_ID_PRESENT != 0 {
+            let block_family = read_u32(block, 28);
+            if !families.contains(&block_family) {
+                families.push(block_family);
+            }
+            if let Some(family_id) = family_id {
+                if block_family != family_id {
+                    continue;
+                }
+            }
+        }
+
+        vec_bin.push(Bin {
+            address: target_address,
+            data: block[32..32 + payload_size].to_vec(),
+        });
+    }
+
+    let families = families
+        .iter()
+        .map(|family| format!("{:#010x}", family))
+        .collect::<Vec<String>>()
+        .join(", ");
+    if family_id.is_none() && families.contains(',') {
+        return Err(format_error(&format!(
+            "file contains several families [{}], please specify the chip",
+            families
+        )));
+    }
+    if vec_bin.is_empty() {
+        return Err(format_error(&match family_id {
+            Some(family_id) => format!("no block for family {:#010x}, file contains [{}]", family_id, families),
+            None => "no block for main flash".to_string(),
+        }));
+    }
+
+    Ok(Firmware {
+        bins: hex_to_bin::merge(vec_bin),
+        start_address: None,
+    })
+}
+
+/// 把数据转换为UF2格式，每个块写入256个字节
+pub fn bin_to_uf2(vec_bin: &[Bin], family_id: Option<u32>) -> Vec<u8> {
+    let num_blocks: usize = vec_bin
+        .iter()
+        .map(|bin| bin.data.len().div_ceil(PAYLOAD_SIZE))
+        .sum();
+
+    let mut uf2 = Vec::with_capacity(num_blocks * BLOCK_SIZE);
+    let mut block_no = 0;
+    for bin in vec_bin {
+        for (i, chunk) in bin.data.chunks(PAYLOAD_SIZE).enumerate() {
+            let mut block = [0u8; BLOCK_SIZE];
+            let flags = if family_id.is_some() { FLAG_FAMILY_ID_PRESENT } else { 0 };
+            let fields = [
+                MAGIC_START0,
+                MAGIC_START1,
+                flags,
+                bin.address + (i * PAYLOAD_SIZE) as u32,
+                chunk.len() as u32,
+                block_no as u32,
+                num_blocks as u32,
+                family_id.unwrap_or(0),
+            ];
+            for (j, field) in fields.iter().enumerate() {
+                block[j * 4..j * 4 + 4].copy_from_slice(&field.to_le_bytes());
+            }
+            block[32..32 + chunk.len()].copy_from_slice(chunk);
+            block[508..512].copy_from_slice(&MAGIC_END.to_le_bytes());
+            uf2.extend_from_slice(&block);
+            block_no += 1;
+        }
+    }
+    uf2
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hex_to_bin::tests::{segments, Segments};
+
+    const FAMILY_A: u32 = 0x1111_1111;
+    const FAMILY_B: u32 = 0x2222_2222;
+
+    /// 生成一个块，family 为 None 时不设置 FLAG_FAMILY_ID_PRESENT
+    fn block(flags: u32, address: u32, data: &[u8], family: Option<u32>) -> Vec<u8> {
+        let flags = flags | if family.is_some() { FLAG_FAMILY_ID_PRESENT } else { 0 };
+        let mut block = vec![0u8; BLOCK_SIZE];
+        let fields = [MAGIC_START0, MAGIC_START1, flags, address, data.len() as u32, 0, 1, family.unwrap_or(0)];
+        for (j, field) in fields.iter().enumerate() {
+            block[j * 4..j * 4 + 4].copy_from_slice(&field.to_le_bytes());
+        }
+        block[32..32 + data.len()].copy_from_slice(data);
+        block[508..512].copy_from_slice(&MAGIC_END.to_le_bytes());
+        block
+    }
+
+    #[test]
+    fn uf2_to_bin_ok() {
+        let two_families = [
+            block(0, 0x0800_0000, &[1, 2], Some(FAMILY_A)),
+            block(0, 0x0800_0000, &[3, 4], Some(FAMILY_B)),
+            block(0, 0x0800_0002, &[5], Some(FAMILY_A)),
+        ]
+        .concat();
+        let cases: &[(&str, Vec<u8>, Option<u32>, Segments)] = &[
+            ("one family", block(0, 0x0800_0000, &[1, 2], Some(FAMILY_A)), None, vec![(0x0800_0000, vec![1, 2])]),
+            ("matching family", block(0, 0x0800_0000, &[1, 2], Some(FAMILY_A)), Some(FAMILY_A), vec![(0x0800_0000, vec![1, 2])]),
+            ("filter family A", two_families.clone(), Some(FAMILY_A), vec![(0x0800_0000, vec![1, 2, 5])]),
+            ("filter family B", two_families.clone(), Some(FAMILY_B), vec![(0x0800_0000, vec![3, 4])]),
+            // 没有 family ID 的块不过滤
+            ("no family id", block(0, 0x0800_0000, &[1], None), Some(FAMILY_A), vec![(0x0800_0000, vec![1])]),
+            // 不连续的块分成多段，乱序的块按地址排序
+            (
+                "non-contiguous",
+                [
+                    block(0, 0x0800_1000, &[3], None),
+                    block(0, 0x0800_0000, &[1], None),
+                    block(0, 0x0800_0001, &[2], None),
+                ]
+                .concat(),
+                None,
+                vec![(0x0800_0000, vec![1, 2]), (0x0800_1000, vec![3])],
+            ),
+            // 不是写入主Flash的块和文件容器被跳过
+            (
+                "skipped blocks",
+                [
+                    block(FLAG_NOT_MAIN_FLASH, 0x0800_1000, &[9], None),
+                    block(FLAG_FILE_CONTAINER, 0x0800_2000, &[9], None),
+                    block(0, 0x0800_0000, &[1], None),
+                ]
+                .concat(),
+                None,
+                vec![(0x0800_0000, vec![1])],
+            ),
+        ];
+        for (name, data, family_id, expected) in cases {
+            let firmware = uf2_to_bin(data, *family_id).unwrap();
+            assert_eq!(segments(&firmware), *expected, "{}", name);
+            assert_eq!(firmware.start_address, None, "{}", name);
+        }
+    }
+
+    #[test]
+    fn uf2_to_bin_err() {
+        let valid = block(0, 0x0800_0000, &[1], Some(FAMILY_A));
+        let with = |offset: usize, value: u8| {
+            let mut data = valid.clone();
+            data[offset] = value;
+            data
+        };
+        let cases: &[(&str, Vec<u8>, Option<u32>)] = &[
+            ("empty", vec![], None),
+            ("size", valid[..BLOCK_SIZE - 1].to_vec(), None),
+            ("magic start0", with(0, 0), None),
+            ("magic start1", with(4, 0), None),
+            ("magic end", with(508, 0), None),
+            ("payload too large", with(17, 0x02), None),
+            ("other family", valid.clone(), Some(FAMILY_B)),
+            (
+                "several families",
+                [block(0, 0x0800_0000, &[1], Some(FAMILY_A)), block(0, 0x0800_0000, &[2], Some(FAMILY_B))].concat(),
+                None,
+            ),
+            ("only skipped blocks", block(FLAG_NOT_MAIN_FLASH, 0x0800_0000, &[1], None), None),
+        ];
+        for (name, data, family_id) in cases {
+            assert!(uf2_to_bin(data, *family_id).is_err(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn bin_to_uf2_round_trip() {
+        let bins = vec![
+            Bin { address: 0x0800_0000, data: (0..=255).chain(0..10).collect() },
+            Bin { address: 0x0800_4000, data: vec![0x55; 3] },
+        ];
+        for family_id in [None, Some(FAMILY_A)] {
+            let data = bin_to_uf2(&bins, family_id);
+            assert_eq!(data.len(), 3 * BLOCK_SIZE);
+            assert!(is_uf2(&data));
+            let firmware = uf2_to_bin(&data, family_id).unwrap();
+            let expected = bins.iter().map(|bin| (bin.address, bin.data.clone())).collect::<Vec<_>>();
+            assert_eq!(segments(&firmware), expected);
+        }
+        assert!(!is_uf2(&[0; BLOCK_SIZE]));
+    }
+}
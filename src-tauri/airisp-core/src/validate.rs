@@ -0,0 +1,54 @@
+use crate::error::Error;
+use colored::Color;
+use rust_i18n::t;
+use crate::hex_to_bin::{self, Bin};
+use crate::log::LOG;
+use crate::peripheral::ChipInfo;
+
+/// 打印各段数据的地址范围和大小
+pub fn print_segment_table(vec_bin: &[Bin]) {
+    LOG.info(t!("segment_table_help").as_str(), Color::BrightBlue);
+    LOG.info_no_color(format!("{:>4}  {:<10}  {:<10}  {:>8}", "#", "start", "end", "size").as_str());
+    for (i, bin) in vec_bin.iter().enumerate() {
+        LOG.info_no_color(format!("{:>4}  {:#010x}  {:#010x}  {:>8}",
+            i,
+            bin.address,
+            bin.address as u64 + bin.data.len() as u64,
+            bin.data.len()
+        ).as_str());
+    }
+}
+
+/// 烧录前检查：各段之间不能重叠，且必须在Flash范围内，也不能超过芯片实际的Flash大小
+pub fn validate(vec_bin: &[Bin], info: &ChipInfo, flash_size: u32) -> Result<(), Error> {
+    print_segment_table(vec_bin);
+
+    hex_to_bin::check_overlap(vec_bin)?;
+
+    let flash_start = info.flash_addr as u64;
+    let flash_end = flash_start + info.flash_size as u64;
+    let detected_end = flash_start + flash_size as u64;
+    for bin in vec_bin {
+        let start = bin.address as u64;
+        let end = start + bin.data.len() as u64;
+        let msg = if start < flash_start || end > flash_end {
+            t!("segment_out_of_flash_help",
+                "start" => format!("{:#010x}", start),
+                "end" => format!("{:#010x}", end),
+                "chip" => info.name,
+                "flash_start" => format!("{:#010x}", flash_start),
+                "flash_end" => format!("{:#010x}", flash_end)
+            )
+        } else if end > detected_end {
+            t!("segment_exceed_flash_size_help",
+                "start" => format!("{:#010x}", start),
+                "end" => format!("{:#010x}", end),
+                "size" => format!("{}", flash_size)
+            )
+        } else {
+            continue;
+        };
+        return Err(Error::InvalidInput(msg));
+    }
+    Ok(())
+}
@@ -1,29 +1,16 @@
 #![allow(non_snake_case)]
-use std::error::Error;
-use std::io::Read;
+use std::sync::Arc;
+use airisp_core::config::Config;
+use airisp_core::config_file::ConfigFile;
+use airisp_core::progress::{Progress, ProgressSink};
+use airisp_core::log::LOG;
+use airisp_core::error::Error;
 use clap::{Arg, ArgMatches, ColorChoice, Command, value_parser};
+use clap::parser::ValueSource;
 use clap::builder::styling;
+use colored::Color;
 use rust_i18n::t;
-use crate::{get, hex_to_bin, write_flash};
-use std::path::Path;
-use std::string::String;
-use crate::log::LOG;
-use crate::peripheral;
-
-#[derive(PartialEq)]
-pub enum Progress {
-    None,
-    Bar,
-    Percent,
-}
-
-#[repr(i32)]
-pub enum ExitCode {
-    Success = 0,
-    PpError = 2, // 外设相关错误
-    FileError = 3, // 文件相关错误
-    NoMatchChip = 4, //没有匹配的芯片 
-}
+use crate::{erase_region, gang, get, go, list_ports, protect, read_flash, write_flash};
 
 pub fn air_isp() -> Command
 {
@@ -50,6 +37,16 @@ pub fn air_isp() -> Command
         .value_parser(value_parser! { u32 })
         .default_value("0");
 
+    let auto_baud = Arg::new("auto_baud")
+        .global(true)
+        .long("auto-baud")
+        .help(t!("auto_baud_help"))
+        .value_parser(value_parser!(bool))
+        .num_args(0..=1)
+        .require_equals(true)
+        .default_missing_value("true")
+        .default_value("false");
+
     let trace = Arg::new("trace")
         .global(true)
         .short('t')
@@ -72,14 +69,12 @@ pub fn air_isp() -> Command
         .global(true)
         .long("before")
         .help(t!("before_help"))
-        .value_parser(["direct_connect", "default_reset"])
         .default_value("default_reset");
 
     let after = Arg::new("after")
         .global(true)
         .long("after")
         .help(t!("after_help"))
-        .value_parser(["hard_reset"])
         .default_value("hard_reset");
 
     let peripheral = Arg::new("peripheral")
@@ -95,6 +90,25 @@ pub fn air_isp() -> Command
         .value_parser(["auto", "en", "zh-CN", "ja"])
         .default_value("auto");
 
+    let config = Arg::new("config")
+        .global(true)
+        .long("config")
+        .help(t!("config_help"));
+
+    let format = Arg::new("format")
+        .global(true)
+        .long("format")
+        .help(t!("format_help"))
+        .value_parser(["text", "json"])
+        .default_value("text");
+
+    let progress = Arg::new("progress")
+        .global(true)
+        .long("progress")
+        .help(t!("progress_help"))
+        .value_parser(["percent", "bar"])
+        .default_value("percent");
+
     let styles = styling::Styles::styled()
         .header(styling::AnsiColor::Green.on_default() | styling::Effects::BOLD)
         .usage(styling::AnsiColor::Green.on_default() | styling::Effects::BOLD)
@@ -112,126 +126,146 @@ pub fn air_isp() -> Command
         .arg(port)
         .arg(chip)
         .arg(baud)
+        .arg(auto_baud)
         .arg(trace)
         .arg(connect_attempts)
         .arg(before)
         .arg(after)
         .arg(peripheral)
         .arg(language)
+        .arg(config)
+        .arg(format)
+        .arg(progress)
         .subcommand(write_flash::command())
+        .subcommand(gang::command())
+        .subcommand(read_flash::command())
+        .subcommand(erase_region::command())
+        .subcommand(protect::protect_command())
+        .subcommand(protect::unprotect_command())
+        .subcommand(protect::write_protect_command())
+        .subcommand(protect::write_unprotect_command())
+        .subcommand(go::command())
         .subcommand(get::chip_id_command())
+        .subcommand(get::bootloader_info_command())
+        .subcommand(list_ports::command())
 }
 
-pub struct AirISP {
-    port: String,
-    baud: u32,
-    chip: String,
-    connect_attempts: u32,
-    before: String,
-    after: String,
-    language: String,
-    peripheral: String,
+/// 解析命令行中的数字，支持0x开头的16进制和10进制
+pub fn parse_number(number: &str) -> Result<u32, Error>
+{
+    let number = number.trim();
+    let value = if number.starts_with("0x") || number.starts_with("0X") {
+        u32::from_str_radix(&number[2..], 16)?
+    } else {
+        number.parse::<u32>()?
+    };
+    Ok(value)
+}
+
+/// 根据 no-progress、progress 参数和输出格式选择进度显示方式
+pub fn progress(matches: &ArgMatches) -> Arc<dyn ProgressSink>
+{
+    let progress = if *matches.get_one::<bool>("no-progress").unwrap() {
+        Progress::None
+    } else if LOG.is_json() {
+        Progress::Json
+    } else if matches.get_one::<String>("progress").unwrap() == "bar" {
+        Progress::Bar
+    } else {
+        Progress::Percent
+    };
+    progress.sink()
 }
 
-impl AirISP
+/// 参数是否在命令行中指定，没有指定时优先使用配置文件中的值
+pub fn from_command_line(matches: &ArgMatches, id: &str) -> bool
 {
-    pub fn new(matches: &ArgMatches) -> AirISP
-    {
-        AirISP {
-            port: matches.get_one::<String>("port").unwrap().to_string(),
-            baud: *matches.get_one::<u32>("baud").unwrap(),
-            connect_attempts: *matches.get_one::<u32>("connect_attempts").unwrap(),
-            before: matches.get_one::<String>("before").unwrap().to_string(),
-            after: matches.get_one::<String>("after").unwrap().to_string(),
-            language: matches.get_one::<String>("language").unwrap().to_string(),
-            chip: matches.get_one::<String>("chip").unwrap().to_string(),
-            peripheral: matches.get_one::<String>("peripheral").unwrap().to_string(),
+    matches.value_source(id) == Some(ValueSource::CommandLine)
+}
+
+/// 读取配置文件，指定了 --config 时用它代替从当前目录向上找到的 airisp.toml
+pub fn config_file(matches: &ArgMatches) -> Result<ConfigFile, Error>
+{
+    let (file, paths) = match matches.get_one::<String>("config") {
+        Some(path) => {
+            let path = std::path::PathBuf::from(path);
+            let mut paths = ConfigFile::user_path()
+                .filter(|user| user.is_file() && *user != path)
+                .into_iter()
+                .collect::<Vec<_>>();
+            paths.push(path);
+            let mut file = ConfigFile::default();
+            for path in paths.iter() {
+                file = file.merge(ConfigFile::load(path)?);
+            }
+            (file, paths)
         }
+        None => ConfigFile::discover()?,
+    };
+    for path in paths {
+        LOG.info(t!("config_file_help", "path" => path.display()).as_str(), Color::White);
     }
+    Ok(file)
+}
 
-    pub fn get_port(&self) -> String
-    {
-        self.port.clone()
+/// 根据配置文件和命令行参数生成配置，命令行参数优先
+///
+/// 复位序列在这里检查，写错时在打开串口之前就报错
+pub fn config(matches: &ArgMatches, file: &ConfigFile) -> Result<Config, Error>
+{
+    let mut builder = file.apply(Config::builder());
+    if from_command_line(matches, "port") || file.port.is_none() {
+        builder = builder.port(matches.get_one::<String>("port").unwrap());
     }
-    pub fn get_baud(&self) -> u32
-    {
-        self.baud
+    if from_command_line(matches, "baud") || file.baud.is_none() {
+        builder = builder.baud(*matches.get_one::<u32>("baud").unwrap());
     }
-    pub fn get_connect_attempts(&self) -> u32
-    {
-        self.connect_attempts
+    if from_command_line(matches, "auto_baud") || file.auto_baud.is_none() {
+        builder = builder.auto_baud(*matches.get_one::<bool>("auto_baud").unwrap());
     }
-
-    pub fn get_before(&self) -> String
-    {
-        self.before.clone()
+    if from_command_line(matches, "connect_attempts") || file.connect_attempts.is_none() {
+        builder = builder.connect_attempts(*matches.get_one::<u32>("connect_attempts").unwrap());
     }
-
-    pub fn get_after(&self) -> String
-    {
-        self.after.clone()
+    if from_command_line(matches, "before") || file.before.is_none() {
+        builder = builder.before(matches.get_one::<String>("before").unwrap());
     }
-
-    pub fn get_language(&self) -> String
-    {
-        self.language.clone()
+    if from_command_line(matches, "after") || file.after.is_none() {
+        builder = builder.after(matches.get_one::<String>("after").unwrap());
     }
-
-    pub fn get_chip(&self) -> String
-    {
-        self.chip.clone()
+    if from_command_line(matches, "chip") || file.chip.is_none() {
+        builder = builder.chip(matches.get_one::<String>("chip").unwrap());
     }
-
-    pub fn get_peripheral(&self) -> String
-    {
-        self.peripheral.clone()
+    if from_command_line(matches, "peripheral") || file.peripheral.is_none() {
+        builder = builder.peripheral(matches.get_one::<String>("peripheral").unwrap());
     }
+    let config = builder.build();
+    config.get_before_sequence()?;
+    config.get_after_sequence()?;
+    Ok(config)
+}
 
-    pub fn get_peripheral_handle(& self) -> Result<peripheral::Peripheral, Box<dyn Error>>
-    {
-        // 全部转换为小写
-        let peripheral = self.get_peripheral().to_lowercase();
-        match peripheral.as_str() {
-            "swd" => {
-                let p = peripheral::Peripheral::Swd(peripheral::swd::Swd::new(&self));
-                Ok(p)
-            },
-            "uart" => {
-                let p = peripheral::Peripheral::GeneralUart(peripheral::general_uart::GeneralUart::new(&self));
-                Ok(p)
-            },
-            _ => {
-                Err(Box::new(std::io::Error::new(std::io::ErrorKind::Other, "not support peripheral")))
-            }
-        }
-    }
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    pub fn read_file(&self, file_path: &str) -> Result<Vec<hex_to_bin::Bin>, Box<dyn Error>>
-    {
-        // 判断文件后缀是.hex还是.bin
-        let mut file = std::fs::File::open(file_path)?;
-        // 如果文件为空，直接返回
-        if file.metadata().unwrap().len() == 0 {
-           return Err(Box::new(std::io::Error::new(std::io::ErrorKind::Other, "file is empty")));
-        }
-        // 读取后缀名
-        let path = Path::new(file_path);
-        let suffix = path.extension().unwrap().to_str().unwrap_or("");
-        let vec_bin = match suffix {
-            "hex" => {
-                let mut hex = String::new();
-                file.read_to_string(&mut hex)?;
-                hex_to_bin::hex_to_bin(&hex)?
-            }
-            "bin" | _ => {
-                let mut bin = Vec::new();
-                file.read_to_end(&mut bin)?;
-                vec![hex_to_bin::Bin {
-                    address: 0xFFFF_FFFF,
-                    data: bin,
-                }]
-            }
+    #[test]
+    fn command_line_over_file() {
+        let file = ConfigFile {
+            port: Some("COM3".to_string()),
+            baud: Some(115200),
+            chip: Some("air001".to_string()),
+            ..Default::default()
         };
-        Ok(vec_bin)
+        // 命令行中给出的值优先，没有给出的使用配置文件中的值
+        let matches = air_isp().try_get_matches_from(["AirISP", "--port", "COM4", "chip_id"]).unwrap();
+        let from_file = config(&matches, &file).unwrap();
+        assert_eq!(from_file.get_port(), "COM4");
+        assert_eq!(from_file.get_baud(), 115200);
+        assert_eq!(from_file.get_chip(), "air001");
+
+        // 没有配置文件时使用命令行参数的默认值
+        let default = config(&matches, &ConfigFile::default()).unwrap();
+        assert_eq!(default.get_baud(), 0);
+        assert_eq!(default.get_chip(), "auto");
     }
 }
@@ -0,0 +1,56 @@
+use airisp_core::error::Error;
+use airisp_core::config::Config;
+use clap::{Arg, ColorChoice, Command};
+use clap::ArgMatches;
+use crate::AirISP;
+use rust_i18n::t;
+
+pub fn command() -> Command
+{
+    let address = Arg::new("address")
+        .id("address")
+        .index(1)
+        .required(true)
+        .help(t!("erase_region_address_help"));
+
+    let size = Arg::new("size")
+        .id("size")
+        .index(2)
+        .required(true)
+        .help(t!("erase_region_size_help"));
+
+    Command::new("erase_region")
+        .about(t!("erase_region_help"))
+        .color(ColorChoice::Auto)
+        .arg(address)
+        .arg(size)
+}
+
+pub struct EraseRegion {
+    address: u32,
+    size: u32,
+    air_isp: Config,
+}
+
+impl EraseRegion {
+    pub fn new(matches: &ArgMatches, air_isp: Config) -> Result<EraseRegion, Error>
+    {
+        Ok(EraseRegion {
+            address: AirISP::parse_number(matches.get_one::<String>("address").unwrap())?,
+            size: AirISP::parse_number(matches.get_one::<String>("size").unwrap())?,
+            air_isp,
+        })
+    }
+
+    pub fn run(&mut self) -> Result<(), Error>
+    {
+        let air_isp = &self.air_isp;
+        let mut binding = air_isp.get_peripheral_handle()?;
+        let p = binding.get_pp();
+
+        p.reset_bootloader()?;
+        p.erase_pages(self.address, self.size)?;
+        p.reset_app()?;
+        Ok(())
+    }
+}
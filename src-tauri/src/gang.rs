@@ -0,0 +1,315 @@
+use std::io::{IsTerminal, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use airisp_core::error::{Error, ExitCode};
+use airisp_core::config::Config;
+use airisp_core::config_file::ConfigFile;
+use airisp_core::event::Event;
+use airisp_core::log::LOG;
+use airisp_core::peripheral::{general_uart, swd};
+use airisp_core::progress::{Operation, ProgressSink};
+use clap::{Arg, ArgAction, ColorChoice, Command};
+use clap::ArgMatches;
+use colored::{Color, Colorize};
+use crossterm::{cursor, queue, terminal};
+use rust_i18n::t;
+use crate::write_flash::{self, WriteFlash};
+
+pub fn command() -> Command
+{
+    let devices = Arg::new("devices")
+        .long("devices")
+        .required(true)
+        .action(ArgAction::Append)
+        .value_delimiter(',')
+        .help(t!("gang_devices_help"));
+
+    Command::new("gang")
+        .about(t!("gang_help"))
+        .color(ColorChoice::Auto)
+        .arg(devices)
+        .args(write_flash::args())
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Stage {
+    Connecting,
+    Reading,
+    Writing,
+    Pass,
+    Fail,
+}
+
+/// 一个设备的烧录状态，由烧录线程更新，主线程显示
+struct Device {
+    port: String,
+    stage: Stage,
+    done: usize,
+    total: usize,
+    elapsed: Duration,
+    /// 失败时的退出码和错误信息
+    error: Option<(i32, String)>,
+}
+
+impl Device {
+    fn finished(&self) -> bool {
+        self.stage == Stage::Pass || self.stage == Stage::Fail
+    }
+
+    fn line(&self, width: usize) -> String {
+        let stage = match self.stage {
+            Stage::Connecting => "connect".normal(),
+            Stage::Reading => "read".bright_blue(),
+            Stage::Writing => "write".bright_blue(),
+            Stage::Pass => "PASS".green(),
+            Stage::Fail => "FAIL".red(),
+        };
+        let percent = match self.stage {
+            Stage::Pass => 100,
+            _ if self.total == 0 => 0,
+            _ => self.done * 100 / self.total,
+        };
+        let filled = percent * 30 / 100;
+        format!("{:<width$}  {:<7}  [{}{}] {:>3}%",
+            self.port,
+            stage,
+            "#".repeat(filled),
+            "-".repeat(30 - filled),
+            percent,
+            width = width
+        )
+    }
+}
+
+/// 把读写进度记录到设备状态中
+struct DeviceProgress {
+    device: Arc<Mutex<Device>>,
+}
+
+impl ProgressSink for DeviceProgress {
+    fn start(&self, operation: Operation, _address: u32, total: usize) {
+        let mut device = self.device.lock().unwrap();
+        device.stage = match operation {
+            Operation::Read => Stage::Reading,
+            Operation::Write => Stage::Writing,
+        };
+        device.done = 0;
+        device.total = total;
+    }
+
+    fn update(&self, done: usize, total: usize) {
+        let mut device = self.device.lock().unwrap();
+        device.done = done;
+        device.total = total;
+    }
+}
+
+/// 简单的通配符匹配，* 匹配任意多个字符，? 匹配一个字符
+fn wildcard_match(pattern: &str, name: &str) -> bool
+{
+    let pattern = pattern.chars().collect::<Vec<char>>();
+    let name = name.chars().collect::<Vec<char>>();
+    // matched[i][j] 表示 pattern 的前 i 个字符能否匹配 name 的前 j 个字符
+    let mut matched = vec![vec![false; name.len() + 1]; pattern.len() + 1];
+    matched[0][0] = true;
+    for i in 1..=pattern.len() {
+        if pattern[i - 1] == '*' {
+            matched[i][0] = matched[i - 1][0];
+        }
+        for j in 1..=name.len() {
+            matched[i][j] = match pattern[i - 1] {
+                '*' => matched[i - 1][j] || matched[i][j - 1],
+                '?' => matched[i - 1][j - 1],
+                c => matched[i - 1][j - 1] && c == name[j - 1],
+            };
+        }
+    }
+    matched[pattern.len()][name.len()]
+}
+
+pub struct Gang {
+    devices: Vec<String>,
+    job: WriteFlash,
+    air_isp: Config,
+}
+
+impl Gang {
+    pub fn new(matches: &ArgMatches, air_isp: Config, file: &ConfigFile) -> Result<Gang, Error>
+    {
+        let patterns = matches.get_many::<String>("devices").unwrap().cloned().collect::<Vec<String>>();
+        Ok(Gang {
+            devices: Gang::expand(&patterns, &air_isp)?,
+            job: WriteFlash::new(matches, air_isp.clone(), file)?,
+            air_isp,
+        })
+    }
+
+    /**
+     * 展开设备列表中的通配符，串口匹配端口名称，调试器匹配序列号或标识符
+     */
+    fn expand(patterns: &[String], air_isp: &Config) -> Result<Vec<String>, Error>
+    {
+        let mut names = None;
+        let mut devices: Vec<String> = Vec::new();
+        for pattern in patterns.iter().map(|pattern| pattern.trim()).filter(|pattern| !pattern.is_empty()) {
+            if !pattern.contains(['*', '?']) {
+                if !devices.iter().any(|device| device == pattern) {
+                    devices.push(pattern.to_string());
+                }
+                continue;
+            }
+            if names.is_none() {
+                names = Some(if air_isp.get_peripheral().eq_ignore_ascii_case("swd") {
+                    swd::list_probes()
+                        .into_iter()
+                        .map(|probe| probe.serial_number.unwrap_or(probe.identifier))
+                        .collect::<Vec<String>>()
+                } else {
+                    general_uart::list_ports()?
+                        .into_iter()
+                        .map(|port| port.name)
+                        .collect::<Vec<String>>()
+                });
+            }
+            for name in names.as_ref().unwrap() {
+                if wildcard_match(pattern, name) && !devices.contains(name) {
+                    devices.push(name.clone());
+                }
+            }
+        }
+        if devices.is_empty() {
+            return Err(Error::InvalidInput(t!("gang_no_device_help")));
+        }
+        Ok(devices)
+    }
+
+    pub fn run(&mut self) -> Result<(), Error>
+    {
+        LOG.info(t!("gang_start_help", "count" => format!("{}", self.devices.len())).as_str(), Color::BrightBlue);
+        let devices = self.devices
+            .iter()
+            .map(|port| Arc::new(Mutex::new(Device {
+                port: port.clone(),
+                stage: Stage::Connecting,
+                done: 0,
+                total: 0,
+                elapsed: Duration::ZERO,
+                error: None,
+            })))
+            .collect::<Vec<_>>();
+
+        std::thread::scope(|scope| -> Result<(), Error> {
+            for device in devices.iter() {
+                let air_isp = self.air_isp.to_builder().port(&device.lock().unwrap().port).build();
+                let progress = Arc::new(DeviceProgress { device: device.clone() });
+                let job = &self.job;
+                scope.spawn(move || {
+                    // 多个设备同时输出会混在一起，只由主线程显示进度和结果
+                    LOG.mute_thread(true);
+                    let start = Instant::now();
+                    // 线程崩溃时也要把设备标记为失败，否则主线程会一直等待它结束
+                    let result = panic::catch_unwind(AssertUnwindSafe(|| job.run_on(&air_isp, progress.clone())));
+                    let mut device = progress.device.lock().unwrap_or_else(PoisonError::into_inner);
+                    device.elapsed = start.elapsed();
+                    match result {
+                        Ok(Ok(_)) => device.stage = Stage::Pass,
+                        Ok(Err(e)) => {
+                            device.stage = Stage::Fail;
+                            device.error = Some((e.exit_code() as i32, e.to_string()));
+                        }
+                        Err(payload) => {
+                            let msg = payload.downcast_ref::<&str>().map(|s| s.to_string())
+                                .or_else(|| payload.downcast_ref::<String>().cloned())
+                                .unwrap_or_default();
+                            device.stage = Stage::Fail;
+                            device.error = Some((ExitCode::GangError as i32, t!("gang_panic_help", "msg" => msg)));
+                        }
+                    }
+                });
+            }
+            self.show_progress(&devices)
+        })?;
+
+        self.summary(&devices)
+    }
+
+    /**
+     * 在终端中每个设备占一行显示进度，Json 模式下输出 device_progress 事件
+     */
+    fn show_progress(&self, devices: &[Arc<Mutex<Device>>]) -> Result<(), Error>
+    {
+        let live = !LOG.is_json() && std::io::stdout().is_terminal();
+        let width = self.devices.iter().map(|port| port.len()).max().unwrap_or(0);
+        let mut reported = vec![None; devices.len()];
+        let mut drawn = false;
+        loop {
+            let finished = devices.iter().all(|device| device.lock().unwrap().finished());
+            if LOG.is_json() {
+                for (device, reported) in devices.iter().zip(reported.iter_mut()) {
+                    let device = device.lock().unwrap();
+                    if device.total != 0 && *reported != Some(device.done) {
+                        *reported = Some(device.done);
+                        LOG.event(&Event::DeviceProgress {
+                            port: device.port.clone(),
+                            done: device.done,
+                            total: device.total,
+                        });
+                    }
+                }
+            } else if live {
+                let mut stdout = std::io::stdout();
+                if drawn {
+                    queue!(stdout, cursor::MoveUp(devices.len() as u16))?;
+                }
+                for device in devices.iter() {
+                    queue!(stdout, terminal::Clear(terminal::ClearType::CurrentLine))?;
+                    writeln!(stdout, "{}", device.lock().unwrap().line(width))?;
+                }
+                stdout.flush()?;
+                drawn = true;
+            }
+            if finished {
+                return Ok(());
+            }
+            std::thread::sleep(Duration::from_millis(200));
+        }
+    }
+
+    /**
+     * 打印每个设备的结果，有设备失败时返回错误
+     */
+    fn summary(&self, devices: &[Arc<Mutex<Device>>]) -> Result<(), Error>
+    {
+        let width = self.devices.iter().map(|port| port.len()).max().unwrap_or(0);
+        let mut failed = 0;
+        LOG.info(t!("gang_summary_help").as_str(), Color::BrightBlue);
+        for device in devices.iter() {
+            let device = device.lock().unwrap();
+            let (code, message) = device.error.clone().unwrap_or((0, String::new()));
+            if device.stage != Stage::Pass {
+                failed += 1;
+            }
+            LOG.event(&Event::DeviceResult {
+                port: device.port.clone(),
+                ok: device.stage == Stage::Pass,
+                code,
+                message: message.clone(),
+                elapsed_ms: device.elapsed.as_millis() as u64,
+            });
+            let result = if device.stage == Stage::Pass { "PASS".green() } else { "FAIL".red() };
+            LOG.info_no_color(format!("{:<width$}  {}  {:>7.2}s  {}",
+                device.port,
+                result,
+                device.elapsed.as_secs_f32(),
+                message,
+                width = width
+            ).as_str());
+        }
+
+        if failed > 0 {
+            return Err(Error::Gang { failed, total: devices.len() });
+        }
+        Ok(())
+    }
+}
@@ -1,7 +1,7 @@
-use std::error::Error;
+use airisp_core::error::Error;
+use airisp_core::config::Config;
 use clap::{ColorChoice, Command};
 use clap::ArgMatches;
-use crate::{AirISP, peripheral};
 use rust_i18n::t;
 
 pub fn chip_id_command() -> Command {
@@ -10,22 +10,37 @@ pub fn chip_id_command() -> Command {
         .color(ColorChoice::Auto)
 }
 
+pub fn bootloader_info_command() -> Command {
+    Command::new("bootloader_info")
+        .about(t!("bootloader_info_help"))
+        .color(ColorChoice::Auto)
+}
+
 pub struct Get {
-    air_isp: AirISP::AirISP,
+    air_isp: Config,
 }
 
 impl Get {
-    pub fn new(_: &ArgMatches, air_isp: AirISP::AirISP) -> Get {
+    pub fn new(_: &ArgMatches, air_isp: Config) -> Get {
         Get {
-            air_isp: air_isp,
+            air_isp,
         }
     }
 
-    pub fn chip_id(&mut self) -> Result<(), Box<dyn Error>> {
+    pub fn chip_id(&mut self) -> Result<(), Error> {
         let air_isp = &self.air_isp;
         let mut binding = air_isp.get_peripheral_handle()?;
-        let mut peripheral = binding.get_pp();
+        let peripheral = binding.get_pp();
         peripheral.get_chip_id()?;
         Ok(())
     }
-}
+
+    pub fn bootloader_info(&mut self) -> Result<(), Error> {
+        let air_isp = &self.air_isp;
+        let mut binding = air_isp.get_peripheral_handle()?;
+        let peripheral = binding.get_pp();
+        peripheral.reset_bootloader()?;
+        peripheral.bootloader_info()?;
+        Ok(())
+    }
+}
\ No newline at end of file
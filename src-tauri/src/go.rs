@@ -0,0 +1,60 @@
+use airisp_core::error::Error;
+use airisp_core::config::Config;
+use clap::{Arg, ColorChoice, Command};
+use clap::ArgMatches;
+use crate::AirISP;
+use rust_i18n::t;
+
+pub fn command() -> Command
+{
+    let address = Arg::new("address")
+        .id("address")
+        .index(1)
+        .required(true)
+        .help(t!("go_address_help"));
+
+    Command::new("go")
+        .about(t!("go_help"))
+        .color(ColorChoice::Auto)
+        .arg(address)
+}
+
+pub struct Go {
+    address: String,
+    air_isp: Config,
+}
+
+impl Go {
+    pub fn new(matches: &ArgMatches, air_isp: Config) -> Go
+    {
+        Go {
+            address: matches.get_one::<String>("address").unwrap().to_string(),
+            air_isp,
+        }
+    }
+
+    pub fn run(&mut self) -> Result<(), Error>
+    {
+        let air_isp = &self.air_isp;
+        // 可以直接给出地址，也可以给出固件文件，从文件中取出入口地址
+        let address = match AirISP::parse_number(self.address.as_str()) {
+            Ok(address) => address,
+            Err(_) => {
+                let firmware = air_isp.read_file(self.address.as_str(), None)?;
+                match firmware.go_address() {
+                    Some(address) if address != 0xFFFFFFFF => address,
+                    _ => {
+                        return Err(Error::InvalidInput("no start address in file".to_string()));
+                    }
+                }
+            }
+        };
+
+        let mut binding = air_isp.get_peripheral_handle()?;
+        let p = binding.get_pp();
+
+        p.reset_bootloader()?;
+        p.go(address)?;
+        Ok(())
+    }
+}
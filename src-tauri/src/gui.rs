@@ -0,0 +1,165 @@
+use std::sync::{Arc, Mutex};
+use airisp_core::config::Config;
+use airisp_core::error::Error;
+use airisp_core::event::Event;
+use airisp_core::flash::{self, WriteOptions};
+use airisp_core::log::LOG;
+use airisp_core::peripheral::general_uart::{self, PortInfo};
+use airisp_core::peripheral::swd::{self, ProbeInfo};
+use airisp_core::progress::ProgressSink;
+use rust_i18n::t;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State};
+
+/// 前端通过这个事件名接收烧录过程中的事件
+pub const EVENT_NAME: &str = "airisp-event";
+
+/// 前端传入的连接设置，和命令行的全局参数一一对应
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Settings {
+    port: String,
+    baud: u32,
+    #[serde(default)]
+    auto_baud: bool,
+    chip: String,
+    peripheral: String,
+    before: String,
+    after: String,
+}
+
+impl Settings {
+    fn config(&self) -> Config {
+        Config::builder()
+            .port(&self.port)
+            .baud(self.baud)
+            .auto_baud(self.auto_baud)
+            .chip(&self.chip)
+            .peripheral(&self.peripheral)
+            .before(&self.before)
+            .after(&self.after)
+            .build()
+    }
+}
+
+/// 要烧录的文件，address 只对没有地址信息的文件（例如bin）生效
+#[derive(Deserialize)]
+pub struct Image {
+    address: u32,
+    path: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FlashOptions {
+    erase_all: bool,
+    erase_pages: bool,
+    diff: bool,
+    verify: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChipSummary {
+    name: String,
+    pid: u16,
+    flash_size: u32,
+}
+
+/// 把读写进度以 progress 事件发送给前端
+struct TauriProgress {
+    handle: AppHandle,
+}
+
+impl ProgressSink for TauriProgress {
+    fn update(&self, done: usize, total: usize) {
+        let _ = self.handle.emit_all(EVENT_NAME, Event::Progress { done, total });
+    }
+}
+
+/// 同一时间只允许一个命令操作硬件，避免重复点击时同一个串口被打开两次、事件交错
+#[derive(Default)]
+pub struct Hardware(Arc<Mutex<()>>);
+
+impl Hardware {
+    /// 在后台线程中执行 f，已经有命令在执行时直接返回错误
+    async fn run<T, F>(&self, f: F) -> Result<T, String>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> Result<T, Error> + Send + 'static,
+    {
+        let lock = self.0.clone();
+        tauri::async_runtime::spawn_blocking(move || -> Result<T, String> {
+            let _guard = lock.try_lock().map_err(|_| t!("gui_busy_help"))?;
+            f().map_err(|e| e.to_string())
+        })
+        .await
+        .map_err(|e| e.to_string())?
+    }
+}
+
+/// 把核心库输出的事件转发给所有窗口
+pub fn setup(app: &mut tauri::App) -> Result<(), Box<dyn std::error::Error>> {
+    let handle = app.handle();
+    LOG.set_listener(Some(Box::new(move |event| {
+        let _ = handle.emit_all(EVENT_NAME, event.clone());
+    })));
+    Ok(())
+}
+
+#[tauri::command]
+pub fn list_ports() -> Result<Vec<PortInfo>, String> {
+    general_uart::list_ports().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn list_probes() -> Vec<ProbeInfo> {
+    swd::list_probes()
+}
+
+#[tauri::command]
+pub async fn detect_chip(hardware: State<'_, Hardware>, settings: Settings) -> Result<ChipSummary, String> {
+    hardware.run(move || {
+        let config = settings.config();
+        let mut binding = config.get_peripheral_handle()?;
+        let p = binding.get_pp();
+        p.reset_bootloader()?;
+        let flash_size = p.get_flash_size()?;
+        let info = p.get_chip_info()?;
+        let summary = ChipSummary {
+            name: info.name.to_string(),
+            pid: info.pid,
+            flash_size,
+        };
+        // 识别完成后让芯片回到用户程序，不要停在bootloader中
+        p.reset_app()?;
+        Ok(summary)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn write_flash(
+    app: AppHandle,
+    hardware: State<'_, Hardware>,
+    settings: Settings,
+    images: Vec<Image>,
+    options: FlashOptions,
+) -> Result<(), String> {
+    hardware.run(move || {
+        let config = settings.config();
+        let images = images
+            .into_iter()
+            .map(|image| (image.address, image.path))
+            .collect::<Vec<(u32, String)>>();
+        let options = WriteOptions {
+            erase_all: options.erase_all,
+            erase_pages: options.erase_pages,
+            diff: options.diff,
+            verify: options.verify,
+            progress: Arc::new(TauriProgress { handle: app }),
+        };
+        flash::write_flash(&config, &images, &options)
+    })
+    .await
+}
@@ -0,0 +1,71 @@
+use airisp_core::error::Error;
+use airisp_core::config::Config;
+use airisp_core::log::LOG;
+use airisp_core::event::Event;
+use airisp_core::peripheral::general_uart;
+use airisp_core::peripheral::swd;
+use clap::{ColorChoice, Command};
+use clap::ArgMatches;
+use colored::Color;
+use rust_i18n::t;
+
+pub fn command() -> Command {
+    Command::new("list_ports")
+        .about(t!("list_ports_help"))
+        .color(ColorChoice::Auto)
+}
+
+pub struct ListPorts;
+
+impl ListPorts {
+    pub fn new(_: &ArgMatches, _: Config) -> ListPorts {
+        ListPorts
+    }
+
+    pub fn run(&mut self) -> Result<(), Error> {
+        // 串口枚举失败时仍然列出调试器
+        let ports = general_uart::list_ports().unwrap_or_else(|e| {
+            LOG.warn(t!("list_ports_serial_fail_help", "error" => e).as_str());
+            Vec::new()
+        });
+        let probes = swd::list_probes();
+
+        if LOG.is_json() {
+            LOG.event(&Event::Ports { ports, probes });
+            return Ok(());
+        }
+
+        // 串口，--peripheral Uart 时传给 --port
+        LOG.info(t!("list_ports_serial_help").as_str(), Color::Green);
+        if ports.is_empty() {
+            LOG.info_no_color(format!("  {}", t!("list_ports_none_help")).as_str());
+        }
+        for port in ports.iter() {
+            let mut line = format!("  {}", port.name);
+            if let (Some(vid), Some(pid)) = (port.vid, port.pid) {
+                line += &format!("  {:04x}:{:04x}", vid, pid);
+            }
+            if let Some(serial_number) = &port.serial_number {
+                line += &format!("  SN: {}", serial_number);
+            }
+            if let Some(product) = &port.product {
+                line += &format!("  {}", product);
+            }
+            LOG.info_no_color(line.as_str());
+        }
+
+        // 调试器，--peripheral Swd 时可以把标识符的一部分传给 --port
+        LOG.info(t!("list_ports_probe_help").as_str(), Color::Green);
+        if probes.is_empty() {
+            LOG.info_no_color(format!("  {}", t!("list_ports_none_help")).as_str());
+        }
+        for probe in probes.iter() {
+            let mut line = format!("  {}  {}  {:04x}:{:04x}", probe.identifier, probe.probe_type, probe.vid, probe.pid);
+            if let Some(serial_number) = &probe.serial_number {
+                line += &format!("  SN: {}", serial_number);
+            }
+            LOG.info_no_color(line.as_str());
+        }
+        Ok(())
+    }
+}
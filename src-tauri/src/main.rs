@@ -3,21 +3,22 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 mod write_flash;
-mod peripheral;
+mod read_flash;
+mod erase_region;
+mod protect;
+mod go;
 mod AirISP;
 mod get;
-mod hex_to_bin;
-mod log;
+mod gang;
+mod list_ports;
+mod gui;
 
 use colored::*;
+use airisp_core::error::Error;
+use airisp_core::event::Event;
+use airisp_core::log::{Format, LOG};
 
-rust_i18n::i18n!("i18n");
-
-// Learn more about Tauri commands at https://tauri.app/v1/guides/features/command
-#[tauri::command]
-fn greet(name: &str) -> String {
-    format!("Hello, {}! You've been greeted from Rust!", name)
-}
+rust_i18n::i18n!("airisp-core/i18n");
 
 fn default_language() {
     let language = whoami::lang().collect::<Vec<String>>();
@@ -25,23 +26,22 @@ fn default_language() {
     let i18n_list = rust_i18n::available_locales!();
     // 不支持的语言默认使用英语
     if !i18n_list.contains(&language.as_str()) {
-        println!("{}","Language not supported");
         rust_i18n::set_locale("en");
     } else {
         rust_i18n::set_locale(&language);
     }
 }
 
-fn set_language(air_isp: &AirISP::AirISP) {
-    let language = if air_isp.get_language() != "auto" {
-        air_isp.get_language().to_owned()
+fn set_language(language: &str) {
+    let language = if language != "auto" {
+        language.to_owned()
     } else {
         let language = whoami::lang().collect::<Vec<String>>();
         let language = language[0].as_str().to_owned();
         let i18n_list = rust_i18n::available_locales!();
         // 不支持的语言默认使用英语
         if !i18n_list.contains(&language.as_str()) {
-            println!("{}","Language not supported");
+            LOG.warn("Language not supported");
             "en".to_owned()
         } else {
             language
@@ -54,28 +54,83 @@ fn cli() {
     default_language();
     let matches = AirISP::air_isp().get_matches();
 
-    let air_isp = AirISP::AirISP::new(&matches);
-    set_language(&air_isp);
+    if matches.get_one::<String>("format").unwrap() == "json" {
+        // 标准输出只包含JSON事件，其他文字不再带颜色
+        LOG.set_format(Format::Json);
+        colored::control::set_override(false);
+    }
+    set_language(matches.get_one::<String>("language").unwrap());
     // 打印版本号
-    println!("AirISP version: {}", env!("CARGO_PKG_VERSION").blue());
+    LOG.info_no_color(format!("AirISP version: {}", env!("CARGO_PKG_VERSION").blue()).as_str());
+    let config_file = AirISP::config_file(&matches).unwrap_or_else(|e| exit_with_error(e));
+    let air_isp = AirISP::config(&matches, &config_file).unwrap_or_else(|e| exit_with_error(e));
     
     if let Some((command, sub_m)) = matches.subcommand() {
-        match command {
+        let result = match command {
             "write_flash" => {
-                let mut wf = write_flash::WriteFlash::new(&sub_m, air_isp);
-                wf.run().unwrap();
+                write_flash::WriteFlash::new(sub_m, air_isp, &config_file).and_then(|mut wf| wf.run())
+            },
+            "gang" => {
+                gang::Gang::new(sub_m, air_isp, &config_file).and_then(|mut gang| gang.run())
+            },
+            "read_flash" => {
+                read_flash::ReadFlash::new(sub_m, air_isp).and_then(|mut rf| rf.run())
+            },
+            "erase_region" => {
+                erase_region::EraseRegion::new(sub_m, air_isp).and_then(|mut er| er.run())
+            },
+            "protect" => {
+                let mut protect = protect::Protect::new(sub_m, air_isp);
+                protect.read_protect()
+            },
+            "unprotect" => {
+                let mut protect = protect::Protect::new(sub_m, air_isp);
+                protect.read_unprotect()
+            },
+            "write_protect" => {
+                let mut protect = protect::Protect::new(sub_m, air_isp);
+                protect.write_protect()
+            },
+            "write_unprotect" => {
+                let mut protect = protect::Protect::new(sub_m, air_isp);
+                protect.write_unprotect()
+            },
+            "go" => {
+                let mut go = go::Go::new(sub_m, air_isp);
+                go.run()
             },
             "chip_id" => {
-                let mut get = get::Get::new(&sub_m, air_isp);
-                get.chip_id().unwrap();
+                let mut get = get::Get::new(sub_m, air_isp);
+                get.chip_id()
+            },
+            "bootloader_info" => {
+                let mut get = get::Get::new(sub_m, air_isp);
+                get.bootloader_info()
+            },
+            "list_ports" => {
+                let mut list = list_ports::ListPorts::new(sub_m, air_isp);
+                list.run()
             },
             _ => {
-                println!("no subcommand");
+                LOG.info_no_color("no subcommand");
+                Ok(())
             }
+        };
+
+        if let Err(e) = result {
+            exit_with_error(e);
         }
     }
 }
 
+/// 所有错误都在这里打印并转换为退出码
+fn exit_with_error(e: Error) -> ! {
+    let code = e.exit_code() as i32;
+    LOG.error(e.to_string().as_str());
+    LOG.event(&Event::Error { code, message: e.to_string() });
+    std::process::exit(code);
+}
+
 fn main() {
     let args: Vec<String> = std::env::args().collect();
     if args.len() > 1 {
@@ -94,7 +149,14 @@ fn main() {
     }
     
     tauri::Builder::default()
-        .invoke_handler(tauri::generate_handler![greet])
+        .manage(gui::Hardware::default())
+        .setup(gui::setup)
+        .invoke_handler(tauri::generate_handler![
+            gui::list_ports,
+            gui::list_probes,
+            gui::detect_chip,
+            gui::write_flash
+        ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
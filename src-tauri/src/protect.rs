@@ -0,0 +1,202 @@
+use airisp_core::error::Error;
+use airisp_core::config::Config;
+use clap::{Arg, ColorChoice, Command, value_parser};
+use clap::ArgMatches;
+use colored::Colorize;
+use crate::AirISP;
+use airisp_core::log::LOG;
+use rust_i18n::t;
+
+pub fn protect_command() -> Command {
+    Command::new("protect")
+        .about(t!("protect_help"))
+        .color(ColorChoice::Auto)
+}
+
+pub fn unprotect_command() -> Command {
+    let yes = Arg::new("yes")
+        .short('y')
+        .long("yes")
+        .help(t!("unprotect_yes_help"))
+        .value_parser(value_parser!(bool))
+        .num_args(0..=1)
+        .require_equals(true)
+        .default_missing_value("true")
+        .default_value("false");
+
+    Command::new("unprotect")
+        .about(t!("unprotect_help"))
+        .color(ColorChoice::Auto)
+        .arg(yes)
+}
+
+pub fn write_protect_command() -> Command {
+    let sectors = Arg::new("sectors")
+        .short('s')
+        .long("sectors")
+        .required(true)
+        .help(t!("write_protect_sectors_help"));
+
+    Command::new("write_protect")
+        .about(t!("write_protect_command_help"))
+        .color(ColorChoice::Auto)
+        .arg(sectors)
+}
+
+pub fn write_unprotect_command() -> Command {
+    Command::new("write_unprotect")
+        .about(t!("write_unprotect_command_help"))
+        .color(ColorChoice::Auto)
+}
+
+/// 解析扇区列表，例如 "0-3,7" 解析为 [0, 1, 2, 3, 7]
+///
+/// 扇区号不能超过 255，芯片实际的扇区数在连接后再检查
+pub fn parse_sectors(sectors: &str) -> Result<Vec<u8>, Error> {
+    let sector = |number: &str| -> Result<u8, Error> {
+        let value = AirISP::parse_number(number)?;
+        u8::try_from(value).map_err(|_| Error::InvalidInput(t!("write_protect_sector_max_help",
+            "sector" => format!("{}", value),
+            "max" => format!("{}", u8::MAX)
+        )))
+    };
+
+    let mut list = Vec::new();
+    for part in sectors.split(',').map(|s| s.trim()).filter(|s| !s.is_empty()) {
+        match part.split_once('-') {
+            Some((start, end)) => {
+                let start = sector(start)?;
+                let end = sector(end)?;
+                if start > end {
+                    return Err(Error::InvalidInput(format!("invalid sector range: {}", part)));
+                }
+                list.extend(start..=end);
+            }
+            None => list.push(sector(part)?),
+        }
+    }
+    list.sort();
+    list.dedup();
+    if list.is_empty() {
+        return Err(Error::InvalidInput("no sector specified".to_string()));
+    }
+    Ok(list)
+}
+
+pub struct Protect {
+    yes: bool,
+    sectors: Option<String>,
+    air_isp: Config,
+}
+
+impl Protect {
+    pub fn new(matches: &ArgMatches, air_isp: Config) -> Protect {
+        Protect {
+            yes: matches.try_get_one::<bool>("yes").ok().flatten().copied().unwrap_or(false),
+            sectors: matches.try_get_one::<String>("sectors").ok().flatten().cloned(),
+            air_isp,
+        }
+    }
+
+    pub fn read_protect(&mut self) -> Result<(), Error> {
+        let air_isp = &self.air_isp;
+        let mut binding = air_isp.get_peripheral_handle()?;
+        let p = binding.get_pp();
+
+        p.reset_bootloader()?;
+        p.read_protect()?;
+        p.reset_app()?;
+        Ok(())
+    }
+
+    pub fn read_unprotect(&mut self) -> Result<(), Error> {
+        // 解除读保护会擦除全片，需要用户确认
+        if !self.yes && !confirm(t!("unprotect_confirm_help").as_str())? {
+            LOG.warn(t!("unprotect_cancel_help").as_str());
+            return Ok(());
+        }
+
+        let air_isp = &self.air_isp;
+        let mut binding = air_isp.get_peripheral_handle()?;
+        let p = binding.get_pp();
+
+        p.reset_bootloader()?;
+        p.read_unprotect()?;
+        p.reset_app()?;
+        Ok(())
+    }
+
+    pub fn write_protect(&mut self) -> Result<(), Error> {
+        let sectors = parse_sectors(self.sectors.as_deref().unwrap_or(""))?;
+
+        let air_isp = &self.air_isp;
+        let mut binding = air_isp.get_peripheral_handle()?;
+        let p = binding.get_pp();
+
+        p.reset_bootloader()?;
+
+        // 根据芯片的Flash大小和扇区大小检查扇区号是否有效
+        let info = p.get_chip_info()?;
+        let sector_count = info.flash_size / info.sector_size;
+        if let Some(sector) = sectors.iter().find(|s| **s as u32 >= sector_count) {
+            let msg = t!("write_protect_sector_invalid_help",
+                "sector" => format!("{}", sector),
+                "max" => format!("{}", sector_count - 1),
+                "size" => format!("{:#x}", info.sector_size)
+            );
+            return Err(Error::InvalidInput(msg));
+        }
+
+        p.write_protect(&sectors)?;
+        p.reset_app()?;
+        Ok(())
+    }
+
+    pub fn write_unprotect(&mut self) -> Result<(), Error> {
+        let air_isp = &self.air_isp;
+        let mut binding = air_isp.get_peripheral_handle()?;
+        let p = binding.get_pp();
+
+        p.reset_bootloader()?;
+        p.write_unprotect()?;
+        p.reset_app()?;
+        Ok(())
+    }
+}
+
+/// 在终端上询问用户，输入y或yes时返回true
+fn confirm(msg: &str) -> Result<bool, Error> {
+    LOG.print(&format!("{} [y/N] ", msg.yellow()));
+    let mut input = String::new();
+    std::io::stdin().read_line(&mut input)?;
+    let input = input.trim().to_lowercase();
+    Ok(input == "y" || input == "yes")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_sectors_ok() {
+        let cases: &[(&str, &[u8])] = &[
+            ("0", &[0]),
+            ("0-3,7", &[0, 1, 2, 3, 7]),
+            ("7, 0-3", &[0, 1, 2, 3, 7]),
+            ("1-2,2-3,,", &[1, 2, 3]),
+            ("0x10-0x11", &[16, 17]),
+            ("255", &[255]),
+            ("254-255", &[254, 255]),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(parse_sectors(input).unwrap(), *expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn parse_sectors_err() {
+        for input in ["", ",", "3-1", "a", "1-b", "256", "0-256", "0-0xFFFFFFFF", "-1"] {
+            assert!(parse_sectors(input).is_err(), "{}", input);
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use airisp_core::error::Error;
+use std::sync::Arc;
+use airisp_core::config::Config;
+use airisp_core::progress::ProgressSink;
+use clap::{Arg, ColorChoice, Command, value_parser};
+use clap::ArgMatches;
+use crate::AirISP;
+use airisp_core::hex_to_bin;
+use rust_i18n::t;
+
+pub fn command() -> Command
+{
+    let no_progress = Arg::new("no-progress")
+        .long("no-progress")
+        .help(t!("no_progress_help"))
+        .value_parser(value_parser!(bool))
+        .num_args(0..=1)
+        .require_equals(true)
+        .default_missing_value("true")
+        .default_value("false");
+
+    let address = Arg::new("address")
+        .id("address")
+        .index(1)
+        .required(true)
+        .help(t!("read_flash_address_help"));
+
+    let size = Arg::new("size")
+        .id("size")
+        .index(2)
+        .required(true)
+        .help(t!("read_flash_size_help"));
+
+    let file_path = Arg::new("path")
+        .id("path")
+        .index(3)
+        .required(true)
+        .help(t!("read_flash_file_path_help"));
+
+    Command::new("read_flash")
+        .about(t!("read_flash_help"))
+        .color(ColorChoice::Auto)
+        .arg(no_progress)
+        .arg(address)
+        .arg(size)
+        .arg(file_path)
+}
+
+pub struct ReadFlash {
+    address: u32,
+    size: u32,
+    file_path: String,
+    progress: Arc<dyn ProgressSink>,
+    air_isp: Config,
+}
+
+impl ReadFlash {
+    pub fn new(matches: &ArgMatches, air_isp: Config) -> Result<ReadFlash, Error>
+    {
+        Ok(ReadFlash {
+            address: AirISP::parse_number(matches.get_one::<String>("address").unwrap())?,
+            size: AirISP::parse_number(matches.get_one::<String>("size").unwrap())?,
+            file_path: matches.get_one::<String>("path").unwrap().to_string(),
+            progress: AirISP::progress(matches),
+
+            air_isp,
+        })
+    }
+
+    pub fn run(&mut self) -> Result<(), Error>
+    {
+        let air_isp = &self.air_isp;
+        let mut binding = air_isp.get_peripheral_handle()?;
+        let p = binding.get_pp();
+
+        p.reset_bootloader()?;
+
+        let data = p.read_flash(self.address, self.size, &self.progress)?;
+        let family_id = p.get_chip_info().ok().map(|chip| chip.uf2_family_id);
+        air_isp.write_file(self.file_path.as_str(), &[hex_to_bin::Bin {
+            address: self.address,
+            data,
+        }], family_id)?;
+
+        p.reset_app()?;
+        Ok(())
+    }
+}
@@ -1,10 +1,16 @@
-use std::error::Error;
+use std::sync::Arc;
+use airisp_core::error::Error;
+use airisp_core::config::Config;
+use airisp_core::config_file::ConfigFile;
+use airisp_core::flash::{self, WriteOptions};
+use airisp_core::progress::ProgressSink;
 use clap::{Arg, ColorChoice, Command, value_parser};
 use clap::ArgMatches;
-use crate::{AirISP, peripheral};
+use crate::AirISP;
 use rust_i18n::t;
 
-pub fn command() -> Command
+/// write_flash 的参数，gang 模式使用同样的参数
+pub fn args() -> Vec<Arg>
 {
     let erase = Arg::new("erase-all")
         .short('e')
@@ -16,6 +22,26 @@ pub fn command() -> Command
         .default_missing_value("true")
         .default_value("false");
 
+    let erase_pages = Arg::new("erase-pages")
+        .long("erase-pages")
+        .help(t!("write_flash_erase_pages_help"))
+        .value_parser(value_parser!(bool))
+        .num_args(0..=1)
+        .require_equals(true)
+        .default_missing_value("true")
+        .default_value("false")
+        .conflicts_with("erase-all");
+
+    let diff = Arg::new("diff")
+        .long("diff")
+        .help(t!("write_flash_diff_help"))
+        .value_parser(value_parser!(bool))
+        .num_args(0..=1)
+        .require_equals(true)
+        .default_missing_value("true")
+        .default_value("false")
+        .conflicts_with_all(["erase-all", "erase-pages"]);
+
     let no_progress = Arg::new("no-progress")
         .long("no-progress")
         .help(t!("no_progress_help"))
@@ -25,82 +51,100 @@ pub fn command() -> Command
         .default_missing_value("true")
         .default_value("false");
 
-    let address = Arg::new("address")
-        .id("address")
+    let verify = Arg::new("verify")
+        .long("verify")
+        .help(t!("write_flash_verify_help"))
+        .value_parser(value_parser!(bool))
+        .num_args(0..=1)
+        .require_equals(true)
+        .default_missing_value("true")
+        .default_value("true");
+
+    let images = Arg::new("images")
+        .id("images")
         .index(1)
-        .required(true)
-        .help(t!("write_flash_address_help"));
+        .required_unless_present("profile")
+        .num_args(2..)
+        .value_names(["address", "path"])
+        .help(t!("write_flash_images_help"));
 
-    let file_path = Arg::new("path")
-        .id("path")
-        .index(2)
-        .required(true)
-        .help(t!("write_flash_file_path_help"));
+    let profile = Arg::new("profile")
+        .long("profile")
+        .help(t!("write_flash_profile_help"));
 
+    vec![erase, erase_pages, diff, no_progress, verify, profile, images]
+}
 
+pub fn command() -> Command
+{
     Command::new("write_flash")
         .about(t!("write_flash_help"))
         .color(ColorChoice::Auto)
-        .arg(erase)
-        .arg(no_progress)
-        .arg(address)
-        .arg(file_path)
-
+        .args(args())
 }
 
 pub struct WriteFlash {
-    address: u32,
-    file_path: String,
-    erase: bool,
-    progress: AirISP::Progress,
-    air_isp: AirISP::AirISP,
+    /// 地址和文件路径对，地址只对没有地址信息的文件（例如bin）生效
+    images: Vec<(u32, String)>,
+    options: WriteOptions,
+    air_isp: Config,
 }
 
 impl WriteFlash {
-    pub fn new(matches: &ArgMatches, air_isp: AirISP::AirISP) -> WriteFlash
+    pub fn new(matches: &ArgMatches, air_isp: Config, file: &ConfigFile) -> Result<WriteFlash, Error>
     {
-        let address = matches.get_one::<String>("address").unwrap();
-        let address = if address.starts_with("0x") || address.starts_with("0X") {
-            u32::from_str_radix(&address[2..], 16).unwrap()
-        } else {
-            address.parse::<u32>().unwrap()
+        let mut images = Vec::new();
+        let mut options = WriteOptions {
+            erase_all: *matches.get_one::<bool>("erase-all").unwrap(),
+            erase_pages: *matches.get_one::<bool>("erase-pages").unwrap(),
+            diff: *matches.get_one::<bool>("diff").unwrap(),
+            verify: *matches.get_one::<bool>("verify").unwrap(),
+            progress: AirISP::progress(matches),
         };
 
-        WriteFlash {
-            address,
-            file_path: matches.get_one::<String>("path").unwrap().to_string(),
-            erase: *matches.get_one::<bool>("erase-all").unwrap(),
-            progress: if *matches.get_one::<bool>("no-progress").unwrap() {
-                AirISP::Progress::None
-            } else {
-                AirISP::Progress::Percent
-            },
+        // 先使用烧录方案中的文件和选项，命令行中指定的选项优先
+        if let Some(name) = matches.get_one::<String>("profile") {
+            let profile = file.profile(name)?;
+            images.extend(profile.images.iter().map(|image| (image.address, image.path.clone())));
+            let option = |id: &str, value: Option<bool>, default: bool| match value {
+                Some(value) if !AirISP::from_command_line(matches, id) => value,
+                _ => default,
+            };
+            options.erase_all = option("erase-all", profile.erase_all, options.erase_all);
+            options.erase_pages = option("erase-pages", profile.erase_pages, options.erase_pages);
+            options.diff = option("diff", profile.diff, options.diff);
+            options.verify = option("verify", profile.verify, options.verify);
+        }
 
-            air_isp,
+        if let Some(args) = matches.get_many::<String>("images") {
+            let args = args.collect::<Vec<&String>>();
+            if !args.len().is_multiple_of(2) {
+                return Err(Error::InvalidInput(t!("write_flash_images_pair_help")));
+            }
+            for pair in args.chunks(2) {
+                images.push((AirISP::parse_number(pair[0])?, pair[1].to_string()));
+            }
         }
+
+        Ok(WriteFlash {
+            images,
+            options,
+            air_isp,
+        })
     }
 
-    pub fn run(&mut self) -> Result<(), Box<dyn Error>>
+    pub fn run(&mut self) -> Result<(), Error>
     {
-        let air_isp = &self.air_isp;
-        let mut binding = air_isp.get_peripheral_handle()?;
-        let p = binding.get_pp();
-
-        p.reset_bootloader()?;
-
-        if self.erase {
-            p.erase_all()?;
-        }
-
-        for bin in air_isp.read_file(self.file_path.as_str())? {
-            if bin.address != 0xFFFFFFFF {
-                p.write_flash(bin.address, &bin.data, AirISP::Progress::Percent)?;
-            } else {
-                p.write_flash(self.address, &bin.data, AirISP::Progress::Percent)?; // 0xFFFFFFFF 代表不指定地址，使用命令行参数指定的地址
-            }
-        }
+        flash::write_flash(&self.air_isp, &self.images, &self.options)
+    }
 
-        p.reset_app()?;
-        Ok(())
+    /// 使用另外的配置和进度显示执行同样的烧录任务，用于 gang 模式
+    pub fn run_on(&self, air_isp: &Config, progress: Arc<dyn ProgressSink>) -> Result<(), Error>
+    {
+        let options = WriteOptions {
+            progress,
+            ..self.options.clone()
+        };
+        flash::write_flash(air_isp, &self.images, &options)
     }
 }